@@ -207,7 +207,9 @@ fn main() {
 
     let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
 
-    let mut model = Model::builder("models/suzanne.obj").build();
+    let mut model = Model::builder("models/suzanne.obj")
+        .build()
+        .expect("Failed to load model");
     model.translate(glam::vec3(0.0, 0.0, -3.0));
 
     let vertex_buffer = CpuAccessibleBuffer::from_iter(
@@ -1,6 +1,11 @@
 use std::{f64::consts::PI, time::Instant};
 
-use learn_vulkano::{light::DirectionalLight, obj_loader::Model, render_system::RenderSystem};
+use learn_vulkano::{
+    animation::{Interpolation, LightAnimation, Player, Repeat, Track},
+    light::DirectionalLight,
+    obj_loader::Model,
+    render_system::RenderSystem,
+};
 
 use vulkano::sync::GpuFuture;
 use winit::{
@@ -14,13 +19,19 @@ fn main() {
 
     system.set_view(&(glam::Mat4::from_translation(glam::vec3(0., 0., -7.))));
 
-    let mut teapot = Model::builder("models/teapot.obj").build();
+    let mut teapot = Model::builder("models/teapot.obj")
+        .build()
+        .expect("Failed to load model");
     teapot.translate(glam::vec3(-5.0, 2.0, -3.0));
 
-    let mut suzanne = Model::builder("models/suzanne.obj").build();
+    let mut suzanne = Model::builder("models/suzanne.obj")
+        .build()
+        .expect("Failed to load model");
     suzanne.translate(glam::vec3(5.0, 2.0, -3.0));
 
-    let mut torus = Model::builder("models/torus.obj").build();
+    let mut torus = Model::builder("models/torus.obj")
+        .build()
+        .expect("Failed to load model");
     torus.translate(glam::vec3(0.0, 0.0, -3.0));
 
     let mut directional_light_r = DirectionalLight {
@@ -32,10 +43,30 @@ fn main() {
         color: [0.0, 1.0, 0.0],
     };
 
+    // The blue light circles the torus, taking 7.2 seconds per lap. A
+    // looping spline through eight points of the circle stays within a
+    // percent of it.
+    let mut directional_light_b = DirectionalLight {
+        position: [2.0, 0.0, -3.0],
+        color: [0.0, 0.0, 1.0],
+    };
+    let mut light_b_path = Track::new(Interpolation::Cubic).repeat(Repeat::Loop);
+    for i in 0..=8 {
+        let angle = i as f32 * std::f32::consts::TAU / 8.0;
+        let position = glam::vec3(2.0 * angle.cos(), 0.0, -3.0 + 2.0 * angle.sin());
+        light_b_path.insert(i as f32 * 0.9, position);
+    }
+    let light_b_animation = LightAnimation {
+        position: Some(light_b_path),
+        color: None,
+    };
+    let mut player = Player::new();
+
     let mut previous_frame_end =
         Some(Box::new(vulkano::sync::now(system.device())) as Box<dyn GpuFuture>);
 
     let rotation_start = Instant::now();
+    let mut last_frame = Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
@@ -61,20 +92,26 @@ fn main() {
                 + rotation_start.elapsed().subsec_nanos() as f64 / 1_000_000_000.0;
             let elapsed_as_radians = elapsed * PI / 180.0;
 
-            teapot.zero_rotation();
-            teapot.rotate(elapsed_as_radians as f32 * 50.0, glam::vec3(0.0, 0.0, 1.0));
-            teapot.rotate(elapsed_as_radians as f32 * 30.0, glam::vec3(0.0, 1.0, 0.0));
-            teapot.rotate(elapsed_as_radians as f32 * 20.0, glam::vec3(1.0, 0.0, 0.0));
-
-            suzanne.zero_rotation();
-            suzanne.rotate(elapsed_as_radians as f32 * 25.0, glam::vec3(0.0, 0.0, 1.0));
-            suzanne.rotate(elapsed_as_radians as f32 * 10.0, glam::vec3(0.0, 1.0, 0.0));
-            suzanne.rotate(elapsed_as_radians as f32 * 60.0, glam::vec3(1.0, 0.0, 0.0));
-
-            torus.zero_rotation();
-            torus.rotate(elapsed_as_radians as f32 * 5.0, glam::vec3(0.0, 0.0, 1.0));
-            torus.rotate(elapsed_as_radians as f32 * 45.0, glam::vec3(0.0, 1.0, 0.0));
-            torus.rotate(elapsed_as_radians as f32 * 12.0, glam::vec3(1.0, 0.0, 0.0));
+            teapot.set_rotation(glam::Quat::from_euler(
+                glam::EulerRot::ZYX,
+                elapsed_as_radians as f32 * 50.0,
+                elapsed_as_radians as f32 * 30.0,
+                elapsed_as_radians as f32 * 20.0,
+            ));
+
+            suzanne.set_rotation(glam::Quat::from_euler(
+                glam::EulerRot::ZYX,
+                elapsed_as_radians as f32 * 25.0,
+                elapsed_as_radians as f32 * 10.0,
+                elapsed_as_radians as f32 * 60.0,
+            ));
+
+            torus.set_rotation(glam::Quat::from_euler(
+                glam::EulerRot::ZYX,
+                elapsed_as_radians as f32 * 5.0,
+                elapsed_as_radians as f32 * 45.0,
+                elapsed_as_radians as f32 * 12.0,
+            ));
 
             directional_light_r.position =
                 glam::Quat::from_rotation_z(elapsed_as_radians as f32 * 0.3)
@@ -85,13 +122,9 @@ fn main() {
                     .mul_vec3(glam::Vec3::from(directional_light_g.position))
                     .into();
 
-            let x = 2.0 * (elapsed_as_radians * 50.).cos();
-            let z = -3.0 + (2.0 * (elapsed_as_radians * 50.).sin());
-
-            let directional_light_b = DirectionalLight {
-                position: [x as f32, 0.0, z as f32],
-                color: [0.0, 0.0, 1.0],
-            };
+            player.advance(last_frame.elapsed().as_secs_f32());
+            last_frame = Instant::now();
+            player.animate_light(&light_b_animation, &mut directional_light_b);
 
             system.start_frame();
             system.render_model(&mut teapot);
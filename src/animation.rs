@@ -0,0 +1,179 @@
+use glam::{Quat, Vec3};
+
+use crate::light::{AmbientLight, DirectionalLight};
+use crate::obj_loader::Model;
+use crate::scene::{NodeId, Scene};
+use crate::transform::Transform;
+
+mod track;
+
+pub use track::{Animatable, Interpolation, Keyframe, Repeat, Track};
+
+/// Tracks for the parts of a [`Transform`]. Parts without a track keep
+/// their current value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransformAnimation {
+    pub translation: Option<Track<Vec3>>,
+    pub rotation: Option<Track<Quat>>,
+    pub scale: Option<Track<Vec3>>,
+}
+
+impl TransformAnimation {
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        if let Some(translation) = sample(&self.translation, time) {
+            transform.translation = translation;
+        }
+        if let Some(rotation) = sample(&self.rotation, time) {
+            transform.rotation = rotation;
+        }
+        if let Some(scale) = sample(&self.scale, time) {
+            transform.scale = scale;
+        }
+    }
+
+    /// The time of the last keyframe of any track.
+    pub fn end(&self) -> f32 {
+        end(&self.translation)
+            .max(end(&self.rotation))
+            .max(end(&self.scale))
+    }
+}
+
+/// Tracks for the position and color of a [`DirectionalLight`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightAnimation {
+    pub position: Option<Track<Vec3>>,
+    pub color: Option<Track<Vec3>>,
+}
+
+impl LightAnimation {
+    pub fn apply(&self, time: f32, light: &mut DirectionalLight) {
+        if let Some(position) = sample(&self.position, time) {
+            light.position = position.to_array();
+        }
+        if let Some(color) = sample(&self.color, time) {
+            light.color = color.to_array();
+        }
+    }
+
+    pub fn end(&self) -> f32 {
+        end(&self.position).max(end(&self.color))
+    }
+}
+
+/// Tracks for an [`AmbientLight`], which is passed on to
+/// [`crate::render_system::RenderSystem::set_ambient`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmbientAnimation {
+    pub color: Option<Track<Vec3>>,
+    pub intensity: Option<Track<f32>>,
+}
+
+impl AmbientAnimation {
+    pub fn apply(&self, time: f32, ambient: &mut AmbientLight) {
+        if let Some(color) = sample(&self.color, time) {
+            ambient.color = color.to_array();
+        }
+        if let Some(intensity) = sample(&self.intensity, time) {
+            ambient.intensity = intensity;
+        }
+    }
+
+    pub fn end(&self) -> f32 {
+        end(&self.color).max(end(&self.intensity))
+    }
+}
+
+/// A clock that animations are sampled at.
+///
+/// The player only moves when [`Player::advance`] is called, usually once a
+/// frame with the time since the last frame, so the same calls always give
+/// the same poses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Player {
+    time: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl Player {
+    pub fn new() -> Player {
+        Player {
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    /// The current time in seconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Jumps to `time` seconds.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// How many seconds of animation one second of [`Player::advance`]
+    /// plays. Negative speeds play backwards.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Moves the clock forward by `seconds` times the speed, unless paused.
+    pub fn advance(&mut self, seconds: f32) {
+        if !self.paused {
+            self.time += seconds * self.speed;
+        }
+    }
+
+    pub fn animate_model(&self, animation: &TransformAnimation, model: &mut Model) {
+        let mut transform = *model.transform();
+        animation.apply(self.time, &mut transform);
+        model.set_transform(transform);
+    }
+
+    /// Animates the local transform of a scene node.
+    pub fn animate_node(&self, animation: &TransformAnimation, scene: &mut Scene, node: NodeId) {
+        animation.apply(self.time, scene.local_mut(node));
+    }
+
+    pub fn animate_light(&self, animation: &LightAnimation, light: &mut DirectionalLight) {
+        animation.apply(self.time, light);
+    }
+
+    pub fn animate_ambient(&self, animation: &AmbientAnimation, ambient: &mut AmbientLight) {
+        animation.apply(self.time, ambient);
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sample<T: Animatable>(track: &Option<Track<T>>, time: f32) -> Option<T> {
+    track.as_ref()?.sample(time)
+}
+
+fn end<T: Animatable>(track: &Option<Track<T>>) -> f32 {
+    track.as_ref().map_or(0.0, Track::end)
+}
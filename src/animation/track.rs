@@ -0,0 +1,353 @@
+use std::ops::{Add, Mul, Sub};
+
+use glam::{Quat, Vec3};
+
+/// How a [`Track`] fills the time between two keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds the value of the earlier keyframe until the next one.
+    Step,
+    /// Blends along a straight line. Rotations are blended component-wise
+    /// and normalized, which is fast but turns unevenly over large angles.
+    #[default]
+    Linear,
+    /// Same as `Linear`, except that rotations turn at a constant speed
+    /// along the shortest arc.
+    Slerp,
+    /// A Catmull-Rom spline through the keyframes, which passes through
+    /// them without sudden changes of speed. Looping tracks also run
+    /// smoothly through their first key, if the last key has the same
+    /// value. Rotations are blended component-wise and normalized like
+    /// `Linear` rather than along a spherical spline, so they turn unevenly
+    /// over large angles between keys.
+    Cubic,
+}
+
+/// What a [`Track`] does outside of the time between its first and last
+/// keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Holds the first value before the start and the last value after the
+    /// end.
+    #[default]
+    Once,
+    /// Starts over from the first keyframe after the last.
+    Loop,
+    /// Plays backwards after the last keyframe, then forwards again.
+    PingPong,
+}
+
+/// A value a [`Track`] can interpolate.
+pub trait Animatable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+    /// Puts an interpolated value back into its valid range, like making a
+    /// rotation unit length.
+    fn normalized(self) -> Self {
+        self
+    }
+
+    /// Flips `self` if needed to take the shorter way from `previous`, for
+    /// values where two representations mean the same thing.
+    fn aligned_with(self, _previous: Self) -> Self {
+        self
+    }
+
+    /// Interpolation at a constant speed, the same as linear interpolation
+    /// unless overridden.
+    fn slerp(a: Self, b: Self, t: f32) -> Self {
+        (a + (b - a) * t).normalized()
+    }
+}
+
+impl Animatable for f32 {}
+
+impl Animatable for Vec3 {}
+
+impl Animatable for Quat {
+    fn normalized(self) -> Self {
+        self.normalize()
+    }
+
+    fn aligned_with(self, previous: Self) -> Self {
+        match self.dot(previous) < 0.0 {
+            true => -self,
+            false => self,
+        }
+    }
+
+    fn slerp(a: Self, b: Self, t: f32) -> Self {
+        a.slerp(b, t)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe<T> {
+    /// In seconds.
+    pub time: f32,
+    pub value: T,
+}
+
+/// Keyframes of one value over time, sampled at any time in between.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    // Sorted by time
+    keyframes: Vec<Keyframe<T>>,
+    interpolation: Interpolation,
+    repeat: Repeat,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(interpolation: Interpolation) -> Track<T> {
+        Track {
+            keyframes: Vec::new(),
+            interpolation,
+            repeat: Repeat::default(),
+        }
+    }
+
+    /// Adds a keyframe at `time` seconds.
+    pub fn key(mut self, time: f32, value: T) -> Track<T> {
+        self.insert(time, value);
+        self
+    }
+
+    pub fn repeat(mut self, repeat: Repeat) -> Track<T> {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Adds a keyframe, replacing the one at the same time if there is one.
+    pub fn insert(&mut self, time: f32, value: T) {
+        let index = self.keyframes.partition_point(|k| k.time < time);
+        match self.keyframes.get_mut(index) {
+            Some(keyframe) if keyframe.time == time => keyframe.value = value,
+            _ => self.keyframes.insert(index, Keyframe { time, value }),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// The time of the last keyframe, or 0 for an empty track.
+    pub fn end(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// The value at `time` seconds, or `None` if the track has no
+    /// keyframes.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let keys = &self.keyframes;
+        let (first, last) = (keys.first()?, keys.last()?);
+        let length = last.time - first.time;
+        let time = match self.repeat {
+            _ if length <= 0.0 => first.time,
+            Repeat::Once => time.clamp(first.time, last.time),
+            Repeat::Loop => first.time + (time - first.time).rem_euclid(length),
+            Repeat::PingPong => {
+                let phase = (time - first.time).rem_euclid(2.0 * length);
+                first.time + length - (phase - length).abs()
+            }
+        };
+
+        let next = keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return Some(first.value);
+        }
+        if next == keys.len() {
+            return Some(last.value);
+        }
+        let (a, b) = (&keys[next - 1], &keys[next]);
+        // Keys are returned as they are, without being normalized again.
+        if time == a.time {
+            return Some(a.value);
+        }
+        let t = (time - a.time) / (b.time - a.time);
+        let b_value = b.value.aligned_with(a.value);
+        Some(match self.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => (a.value + (b_value - a.value) * t).normalized(),
+            Interpolation::Slerp => T::slerp(a.value, b_value, t),
+            Interpolation::Cubic => {
+                // The tangent at each end of the segment comes from its
+                // neighbours, or from the segment itself at the ends of the
+                // track. Looping tracks take the neighbours across the loop
+                // point instead, where the last key stands in for the first.
+                let looping = self.repeat == Repeat::Loop && keys.len() > 2;
+                let before = match next.checked_sub(2) {
+                    Some(i) => keys[i],
+                    None if looping => Keyframe {
+                        time: keys[keys.len() - 2].time - length,
+                        value: keys[keys.len() - 2].value,
+                    },
+                    None => *a,
+                };
+                let after = match keys.get(next + 1) {
+                    Some(key) => *key,
+                    None if looping => Keyframe {
+                        time: keys[1].time + length,
+                        value: keys[1].value,
+                    },
+                    None => *b,
+                };
+                let before_value = before.value.aligned_with(a.value);
+                let after_value = after.value.aligned_with(b_value);
+                let span = b.time - a.time;
+                let a_tangent = (b_value - before_value) * (span / (b.time - before.time));
+                let b_tangent = (after_value - a.value) * (span / (after.time - a.time));
+                let t2 = t * t;
+                let t3 = t2 * t;
+                (a.value * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + a_tangent * (t3 - 2.0 * t2 + t)
+                    + b_value * (-2.0 * t3 + 3.0 * t2)
+                    + b_tangent * (t3 - t2))
+                    .normalized()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    const MODES: [Interpolation; 4] = [
+        Interpolation::Step,
+        Interpolation::Linear,
+        Interpolation::Slerp,
+        Interpolation::Cubic,
+    ];
+
+    fn ramp(repeat: Repeat) -> Track<f32> {
+        Track::new(Interpolation::Linear)
+            .key(1.0, 0.0)
+            .key(3.0, 2.0)
+            .repeat(repeat)
+    }
+
+    #[test]
+    fn empty_and_single_key_tracks() {
+        for mode in MODES {
+            assert_eq!(Track::<f32>::new(mode).sample(0.0), None);
+            for repeat in [Repeat::Once, Repeat::Loop, Repeat::PingPong] {
+                let track = Track::new(mode).key(2.0, 5.0).repeat(repeat);
+                for time in [-1.0, 2.0, 7.5] {
+                    assert_eq!(track.sample(time), Some(5.0));
+                }
+            }
+        }
+        assert_eq!(Track::<f32>::new(Interpolation::Linear).end(), 0.0);
+    }
+
+    #[test]
+    fn once_clamps_to_the_first_and_last_keys() {
+        let track = ramp(Repeat::Once);
+        assert_eq!(track.sample(-5.0), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(0.0));
+        assert_eq!(track.sample(3.0), Some(2.0));
+        assert_eq!(track.sample(100.0), Some(2.0));
+    }
+
+    #[test]
+    fn loop_starts_over_at_the_end() {
+        let track = ramp(Repeat::Loop);
+        assert_eq!(track.end(), 3.0);
+        assert_eq!(track.sample(track.end()), Some(0.0));
+        assert_eq!(track.sample(3.5), Some(0.5));
+        assert_eq!(track.sample(6.5), Some(1.5));
+        assert_eq!(track.sample(0.5), Some(1.5));
+    }
+
+    #[test]
+    fn ping_pong_turns_around_at_the_end() {
+        let track = ramp(Repeat::PingPong);
+        assert_eq!(track.sample(track.end()), Some(2.0));
+        assert_eq!(track.sample(3.5), Some(1.5));
+        assert_eq!(track.sample(5.0), Some(0.0));
+        assert_eq!(track.sample(5.5), Some(0.5));
+        assert_eq!(track.sample(0.5), Some(0.5));
+    }
+
+    #[test]
+    fn every_mode_hits_the_keys_exactly() {
+        let values = [
+            Vec3::new(0.0, 1.0, 2.0),
+            Vec3::new(0.3, -1.7, 4.1),
+            Vec3::new(2.2, 0.1, -0.6),
+            Vec3::new(-1.0, 3.3, 0.9),
+        ];
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(1.3),
+            Quat::from_rotation_x(-0.4) * Quat::from_rotation_z(2.0),
+            Quat::from_rotation_z(0.7),
+        ];
+        let times = [0.0, 0.4, 1.5, 1.6];
+        for mode in MODES {
+            let mut track = Track::new(mode);
+            let mut rotation_track = Track::new(mode);
+            for ((time, value), rotation) in times.iter().zip(values).zip(rotations) {
+                track.insert(*time, value);
+                rotation_track.insert(*time, rotation);
+            }
+            for ((time, value), rotation) in times.iter().zip(values).zip(rotations) {
+                assert_eq!(track.sample(*time), Some(value), "{:?} at {}", mode, time);
+                let sampled = rotation_track.sample(*time).unwrap();
+                assert_eq!(sampled, rotation, "{:?} at {}", mode, time);
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_are_aligned_with_the_previous_key() {
+        let previous = Quat::from_rotation_y(0.2);
+        let same_side = Quat::from_rotation_y(0.5);
+        assert_eq!(same_side.aligned_with(previous), same_side);
+        assert_eq!((-same_side).aligned_with(previous), same_side);
+
+        // Both signs of a key give the same short turn
+        for key in [same_side, -same_side] {
+            let track = Track::new(Interpolation::Linear)
+                .key(0.0, previous)
+                .key(1.0, key);
+            let halfway = track.sample(0.5).unwrap();
+            assert!(halfway.abs_diff_eq(Quat::from_rotation_y(0.35), 1e-5));
+        }
+    }
+
+    #[test]
+    fn looping_cubic_tracks_are_smooth_through_the_first_key() {
+        // Eight points on a circle, back at the start after 8 seconds
+        let point = |i: i32| {
+            let angle = i as f32 * std::f32::consts::TAU / 8.0;
+            Vec3::new(angle.cos(), angle.sin(), 0.0)
+        };
+        let mut track = Track::new(Interpolation::Cubic).repeat(Repeat::Loop);
+        for i in 0..=8 {
+            track.insert(i as f32, point(i));
+        }
+
+        let h = 1e-2;
+        let velocity = |time: f32| {
+            (track.sample(time + h).unwrap() - track.sample(time - h).unwrap()) / (2.0 * h)
+        };
+        // Across the loop point the path turns like it does at every other
+        // key.
+        let at_loop = velocity(0.0);
+        let at_key = velocity(2.0);
+        assert!((at_loop.length() - at_key.length()).abs() < 1e-3);
+        assert!(at_loop.normalize().abs_diff_eq(Vec3::Y, 1e-3), "{at_loop}");
+        // and stays close to the circle.
+        for step in 0..80 {
+            let sampled = track.sample(step as f32 * 0.1).unwrap();
+            assert!((sampled.length() - 1.0).abs() < 0.01, "{sampled}");
+        }
+    }
+}
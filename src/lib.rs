@@ -1,5 +1,9 @@
+pub mod animation;
 pub mod light;
 pub mod mvp;
 pub mod obj_loader;
+pub mod ray;
 pub mod render_system;
+pub mod scene;
 pub mod setup;
+pub mod transform;
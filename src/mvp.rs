@@ -1,9 +1,11 @@
 use std::f32::consts::FRAC_PI_2;
 
 use bytemuck::{Pod, Zeroable};
-use glam::Mat4;
+use glam::{Mat4, Vec2, Vec3};
 use vulkano::swapchain::Surface;
 
+use crate::ray::Ray;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct MVP {
@@ -52,6 +54,19 @@ impl VP {
             projection,
         }
     }
+
+    /// The world space ray through `point`, in pixels from the top left
+    /// corner of a viewport of `extent` pixels, like a cursor position. It
+    /// starts on the near plane of a projection with OpenGL depth, like the
+    /// one of [`VP::from_surface`].
+    pub fn screen_ray(&self, point: Vec2, extent: Vec2) -> Ray {
+        // Vulkan's normalized device coordinates point down, like pixels.
+        let ndc = point / extent * 2.0 - Vec2::ONE;
+        let inverse = (self.projection * self.view).inverse();
+        let near = inverse.project_point3(Vec3::new(ndc.x, ndc.y, -1.0));
+        let far = inverse.project_point3(Vec3::new(ndc.x, ndc.y, 1.0));
+        Ray::new(near, far - near)
+    }
 }
 
 impl Default for VP {
@@ -59,3 +74,56 @@ impl Default for VP {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_rays_start_at_the_camera() {
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let forward = Vec3::new(-1.0, -0.5, -2.0).normalize();
+        let vp = VP {
+            view: Mat4::look_at_rh(eye, eye + forward, Vec3::Y),
+            projection: Mat4::perspective_rh_gl(FRAC_PI_2, 1.5, 0.01, 100.0),
+        };
+        let extent = Vec2::new(1200.0, 800.0);
+
+        let center = vp.screen_ray(extent / 2.0, extent);
+        assert!(center.direction.abs_diff_eq(forward, 1e-4), "{center:?}");
+        assert!(center.origin.abs_diff_eq(eye + forward * 0.01, 1e-4));
+
+        // Pixels to the right look to the right of the camera.
+        let right = forward.cross(Vec3::Y).normalize();
+        let ray = vp.screen_ray(Vec2::new(1200.0, 400.0), extent);
+        assert!(ray.direction.dot(right) > 0.0);
+        // At the edge of a 90 degree vertical field of view, the horizontal
+        // angle depends on the aspect ratio.
+        let tangent = ray.direction.dot(right) / ray.direction.dot(forward);
+        assert!((tangent - 1.5).abs() < 1e-3, "{tangent}");
+    }
+
+    #[test]
+    fn screen_rays_follow_the_drawn_image_vertically() {
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let forward = Vec3::new(-1.0, -0.5, -2.0).normalize();
+        let vp = VP {
+            view: Mat4::look_at_rh(eye, eye + forward, Vec3::Y),
+            projection: Mat4::perspective_rh_gl(FRAC_PI_2, 1.5, 0.01, 100.0),
+        };
+        let extent = Vec2::new(1200.0, 800.0);
+        let right = forward.cross(Vec3::Y).normalize();
+        let up = right.cross(forward);
+
+        // A projection with OpenGL's y up puts the camera's up vector at the
+        // bottom of a Vulkan viewport, so picks have to look down from the
+        // top row for them to land on what is drawn there.
+        let top = vp.screen_ray(Vec2::new(600.0, 0.0), extent);
+        assert!(top.direction.dot(up) < 0.0, "{top:?}");
+        let bottom = vp.screen_ray(Vec2::new(600.0, 800.0), extent);
+        assert!(bottom.direction.dot(up) > 0.0, "{bottom:?}");
+        // At the edge of a 90 degree vertical field of view
+        let tangent = bottom.direction.dot(up) / bottom.direction.dot(forward);
+        assert!((tangent - 1.0).abs() < 1e-3, "{tangent}");
+    }
+}
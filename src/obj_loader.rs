@@ -1,69 +1,316 @@
 use std::cell::Cell;
 use std::fmt;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, Read};
+use std::sync::Arc;
 
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Quat, Vec3};
+
+use crate::ray::{Ray, RayHit};
+use crate::transform::Transform;
+
+mod bounds;
+mod cache;
+mod export;
+mod gltf_import;
+mod material;
+mod mesh;
+mod normals;
+mod placement;
+mod ply;
+mod primitives;
+mod registry;
+mod resolver;
+mod stl;
+mod triangulate;
+mod validate;
+mod winding;
+
+pub use bounds::{Aabb, Sphere};
+pub use export::ObjWriter;
+pub use gltf_import::{GltfNode, GltfScene, PbrMaterial, TextureSource};
+pub use material::Material;
+pub use mesh::{IndexedMesh, Indices, Mesh, SubMesh};
+pub use normals::NormalWeighting;
+pub use placement::{Normalize, Recenter, UpAxis};
+pub use ply::PlyLoader;
+pub use primitives::Primitive;
+pub use registry::MeshRegistry;
+pub use resolver::{FileResolver, Resolver};
+pub use stl::StlLoader;
+pub use validate::{Repair, RepairReport, ValidationReport};
+pub use winding::{CullMode, Winding};
+
+/// Errors produced while loading a model or reading a mesh cache.
+///
+/// Every variant carries the path of the file it is about, so that callers
+/// can report the problem instead of aborting. Errors in the text of obj,
+/// mtl, ply and stl files also carry the (1-based) line number and the
+/// token that could not be handled. Caches, glTF files and unsupported
+/// features are described by a reason, the underlying error or the name
+/// of the feature instead.
+#[derive(Debug)]
+pub enum ObjError {
+    /// The file could not be opened or read.
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    /// A token that should have been a number could not be parsed as one.
+    InvalidNumber {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// A face references a vertex, normal or texture coordinate that has
+    /// not been defined.
+    IndexOutOfRange {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// A statement is missing components it requires, e.g. a face with
+    /// fewer than three corners.
+    Malformed {
+        path: String,
+        line: usize,
+        token: String,
+    },
+    /// A binary mesh cache is corrupt, truncated or from another version.
+    InvalidCache { path: String, reason: &'static str },
+    /// A glTF file or one of its buffers could not be read.
+    Gltf { path: String, source: gltf::Error },
+    /// The file uses a feature the loader can't handle, e.g. a glTF
+    /// primitive made of lines.
+    Unsupported { path: String, feature: String },
+}
+
+impl ObjError {
+    // The raw parsers only know about the token they choke on, so they
+    // report errors with an empty path and line 0. The loader then attaches
+    // the location with this method.
+    fn at(self, file_name: &str, line_number: usize) -> ObjError {
+        let path = file_name.to_string();
+        let line = line_number;
+        match self {
+            ObjError::Io { source, .. } => ObjError::Io { path, source },
+            ObjError::InvalidNumber { token, .. } => ObjError::InvalidNumber { path, line, token },
+            ObjError::IndexOutOfRange { token, .. } => {
+                ObjError::IndexOutOfRange { path, line, token }
+            }
+            ObjError::Malformed { token, .. } => ObjError::Malformed { path, line, token },
+            ObjError::InvalidCache { reason, .. } => ObjError::InvalidCache { path, reason },
+            ObjError::Gltf { source, .. } => ObjError::Gltf { path, source },
+            ObjError::Unsupported { feature, .. } => ObjError::Unsupported { path, feature },
+        }
+    }
+
+    fn invalid_number(token: &str) -> ObjError {
+        ObjError::InvalidNumber {
+            path: String::new(),
+            line: 0,
+            token: token.to_string(),
+        }
+    }
+
+    fn index_out_of_range(token: &str) -> ObjError {
+        ObjError::IndexOutOfRange {
+            path: String::new(),
+            line: 0,
+            token: token.to_string(),
+        }
+    }
+
+    fn invalid_cache(reason: &'static str) -> ObjError {
+        ObjError::InvalidCache {
+            path: String::new(),
+            reason,
+        }
+    }
+
+    fn unsupported(feature: &str) -> ObjError {
+        ObjError::Unsupported {
+            path: String::new(),
+            feature: feature.to_string(),
+        }
+    }
+
+    fn malformed(token: &str) -> ObjError {
+        ObjError::Malformed {
+            path: String::new(),
+            line: 0,
+            token: token.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {}", path, source),
+            ObjError::InvalidNumber { path, line, token } => {
+                write!(f, "{}:{}: invalid number `{}`", path, line, token)
+            }
+            ObjError::IndexOutOfRange { path, line, token } => {
+                write!(f, "{}:{}: index out of range in `{}`", path, line, token)
+            }
+            ObjError::Malformed { path, line, token } => {
+                write!(f, "{}:{}: malformed statement `{}`", path, line, token)
+            }
+            ObjError::InvalidCache { path, reason } => {
+                write!(f, "{}: invalid mesh cache: {}", path, reason)
+            }
+            ObjError::Gltf { path, source } => write!(f, "{}: {}", path, source),
+            ObjError::Unsupported { path, feature } => {
+                write!(f, "{}: unsupported {}", path, feature)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Gltf { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 pub struct RawVertex {
     pub vals: [f32; 3],
 }
 
 impl RawVertex {
-    pub fn new(inpt: &str) -> RawVertex {
-        let items = inpt.split_whitespace();
-        let mut content: Vec<f32> = Vec::new();
-        for item in items {
-            content.push(item.parse().unwrap());
+    /// Parses the numbers of a `v`, `vn` or `vt` statement. Missing
+    /// components are 0, e.g. for `vt u` or `vt u v`, and components past
+    /// the third are ignored, like the `w` of `v x y z w` and the vertex
+    /// colors of `v x y z r g b`.
+    pub fn new(inpt: &str) -> Result<RawVertex, ObjError> {
+        let mut vals = [0.0; 3];
+        let mut count = 0;
+        for item in inpt.split_whitespace() {
+            let val = item.parse().map_err(|_| ObjError::invalid_number(item))?;
+            if let Some(v) = vals.get_mut(count) {
+                *v = val;
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return Err(ObjError::malformed(inpt.trim()));
         }
-        if content.len() == 2 {
-            content.push(0.0);
+        Ok(RawVertex { vals })
+    }
+}
+
+/// A single corner of a face statement, holding the 0-based index of its
+/// vertex and, when present, of its texture coordinate and normal.
+///
+/// All four corner forms `v`, `v/vt`, `v//vn` and `v/vt/vn` are accepted.
+#[derive(Clone, Copy, Debug)]
+pub struct RawCorner {
+    pub vert: usize,
+    pub text: Option<usize>,
+    pub norm: Option<usize>,
+}
+
+impl RawCorner {
+    /// Parses a corner token. `verts`, `text` and `norms` are the number of
+    /// elements of each kind defined so far; they are used to resolve
+    /// negative (relative) indices and to reject out of range ones.
+    pub fn new(inpt: &str, verts: usize, text: usize, norms: usize) -> Result<RawCorner, ObjError> {
+        let mut parts = inpt.split('/');
+        let vert = match parts.next() {
+            Some("") | None => return Err(ObjError::malformed(inpt)),
+            Some(x) => RawCorner::parse_index(x, verts)?,
+        };
+        let mut optional = |count: usize| match parts.next() {
+            Some("") | None => Ok(None),
+            Some(x) => RawCorner::parse_index(x, count).map(Some),
+        };
+        let text = optional(text)?;
+        let norm = optional(norms)?;
+        if parts.next().is_some() {
+            return Err(ObjError::malformed(inpt));
         }
-        RawVertex {
-            vals: content.try_into().unwrap(),
+        Ok(RawCorner { vert, text, norm })
+    }
+
+    // Positive indices count from the start of the file starting at 1,
+    // negative ones count back from the last element read so far (-1 is the
+    // most recent one). 0 is never valid.
+    fn parse_index(token: &str, count: usize) -> Result<usize, ObjError> {
+        let index: isize = token.parse().map_err(|_| ObjError::invalid_number(token))?;
+        let resolved = if index > 0 {
+            index.unsigned_abs() - 1
+        } else {
+            count.wrapping_sub(index.unsigned_abs())
+        };
+        if resolved >= count {
+            return Err(ObjError::index_out_of_range(token));
         }
+        Ok(resolved)
     }
 }
 
+/// A triangle of the loaded mesh. Faces with more than three corners are
+/// split into several `RawFace` values by the loader.
 pub struct RawFace {
     pub verts: [usize; 3],
     pub norms: Option<[usize; 3]>,
     pub text: Option<[usize; 3]>,
+    /// The smoothing group set by the last `s` statement, 0 if smoothing is
+    /// off. Only used when the face has no normals of its own.
+    pub smoothing: u32,
+    /// Index into [`Loader::materials`] of the material set by the last
+    /// `usemtl` statement, if any.
+    pub material: Option<usize>,
+    /// Index into the object and group names of the loader, see
+    /// [`Loader::as_sub_meshes`].
+    pub sub_mesh: usize,
 }
 
 impl RawFace {
-    // call with invert = true if the models are using a clockwise winding order
+    // call with invert = true if the file is counter-clockwise, see
+    // `Winding::needs_invert`
     //
     // Blender files are a common example
-    pub fn new(raw_arg: &str, invert: bool) -> RawFace {
-        let arguments: Vec<&str> = raw_arg.split_whitespace().collect();
+    pub fn new(corners: [RawCorner; 3], invert: bool) -> RawFace {
+        let [a, b, c] = if invert {
+            [corners[0], corners[2], corners[1]]
+        } else {
+            corners
+        };
+        let all = |x: Option<usize>, y: Option<usize>, z: Option<usize>| Some([x?, y?, z?]);
         RawFace {
-            verts: RawFace::parse(arguments.clone(), 0, invert).unwrap(),
-            norms: RawFace::parse(arguments.clone(), 2, invert),
-            text: RawFace::parse(arguments.clone(), 1, invert),
-        }
-    }
-
-    fn parse(inpt: Vec<&str>, index: usize, invert: bool) -> Option<[usize; 3]> {
-        let f1: Vec<&str> = inpt.first().unwrap().split('/').collect();
-        let f2: Vec<&str> = inpt.get(1).unwrap().split('/').collect();
-        let f3: Vec<&str> = inpt.get(2).unwrap().split('/').collect();
-        let a1 = f1.get(index).unwrap().to_string();
-        let a2 = f2.get(index).unwrap().to_string();
-        let a3 = f3.get(index).unwrap().to_string();
-        match a1.as_str() {
-            "" => None,
-            _ => {
-                let p1: usize = a1.parse().unwrap();
-                let (p2, p3): (usize, usize) = if invert {
-                    (a3.parse().unwrap(), a2.parse().unwrap())
-                } else {
-                    (a2.parse().unwrap(), a3.parse().unwrap())
-                };
-                Some([p1 - 1, p2 - 1, p3 - 1]) // .obj files aren't 0-index
-            }
+            verts: [a.vert, b.vert, c.vert],
+            norms: all(a.norm, b.norm, c.norm),
+            text: all(a.text, b.text, c.text),
+            smoothing: 0,
+            material: None,
+            sub_mesh: 0,
+        }
+    }
+
+    /// Parses every corner of a face statement. Faces may have any number
+    /// of corners greater than two.
+    ///
+    /// See [`RawCorner::new`] for the meaning of `verts`, `text` and `norms`.
+    pub fn parse_corners(
+        raw_arg: &str,
+        verts: usize,
+        text: usize,
+        norms: usize,
+    ) -> Result<Vec<RawCorner>, ObjError> {
+        let corners = raw_arg
+            .split_whitespace()
+            .map(|corner| RawCorner::new(corner, verts, text, norms))
+            .collect::<Result<Vec<RawCorner>, ObjError>>()?;
+        if corners.len() < 3 {
+            return Err(ObjError::malformed(raw_arg.trim()));
         }
+        Ok(corners)
     }
 }
 
@@ -97,72 +344,379 @@ pub struct Loader {
     #[allow(unused)]
     text: Vec<RawVertex>,
     faces: Vec<RawFace>,
-    #[allow(unused)]
+    materials: Vec<Material>,
+    sub_mesh_names: Vec<(String, String)>,
     invert_winding_order: bool,
+    // Set by `set_winding_order`. Generated normals point to the side faces
+    // are counter-clockwise from, so they are turned around for clockwise
+    // files.
+    clockwise: bool,
+    normal_weighting: NormalWeighting,
+    crease_angle: Option<f32>,
 }
 
 impl Loader {
-    pub fn new(file_name: &str, custom_color: [f32; 3], invert_winding_order: bool) -> Loader {
+    pub fn new(
+        file_name: &str,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<Loader, ObjError> {
+        let input = FileResolver
+            .open(file_name)
+            .map_err(|source| ObjError::Io {
+                path: file_name.to_string(),
+                source,
+            })?;
+        Loader::from_reader(
+            input,
+            file_name,
+            &FileResolver,
+            custom_color,
+            invert_winding_order,
+        )
+    }
+
+    /// Reads an obj file from any buffered source. `file_name` is used to
+    /// report errors and is passed on to `resolver`, which opens the mtl
+    /// libraries referenced by the file.
+    pub fn from_reader(
+        input: impl BufRead,
+        file_name: &str,
+        resolver: &dyn Resolver,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<Loader, ObjError> {
         let color = custom_color;
-        let input = File::open(file_name).unwrap();
-        let buffered = BufReader::new(input);
+        let io_error = |source| ObjError::Io {
+            path: file_name.to_string(),
+            source,
+        };
         let mut verts: Vec<RawVertex> = Vec::new();
         let mut norms: Vec<RawVertex> = Vec::new();
         let mut text: Vec<RawVertex> = Vec::new();
         let mut faces: Vec<RawFace> = Vec::new();
-        for raw_line in buffered.lines() {
-            let line = raw_line.unwrap();
-            if line.len() > 2 {
-                match line.split_at(2) {
-                    ("v ", x) => {
-                        verts.push(RawVertex::new(x));
+        let mut materials: Vec<Material> = Vec::new();
+        let mut smoothing = 0;
+        let mut material = None;
+        let mut sub_mesh_names = vec![(String::new(), String::new())];
+        let mut sub_mesh = 0;
+        for (line_index, raw_line) in input.lines().enumerate() {
+            let line = raw_line.map_err(io_error)?;
+            let line = line.trim();
+            let (keyword, x) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let parsed = match keyword {
+                "v" => RawVertex::new(x).map(|v| verts.push(v)),
+                "vn" => RawVertex::new(x).map(|v| norms.push(v)),
+                "vt" => RawVertex::new(x).map(|v| text.push(v)),
+                "f" => Loader::parse_face(x, invert_winding_order, &verts, norms.len(), text.len())
+                    .map(|triangles| {
+                        faces.extend(triangles.into_iter().map(|face| RawFace {
+                            smoothing,
+                            material,
+                            sub_mesh,
+                            ..face
+                        }))
+                    }),
+                "s" => Loader::parse_smoothing(x).map(|group| smoothing = group),
+                // Errors inside a library are reported with the location in
+                // the library itself. A library that can't be read is
+                // skipped, and its materials fall back to the custom color
+                // like unknown ones.
+                "mtllib" => {
+                    for library in x.split_whitespace() {
+                        let library = resolver.resolve(file_name, library);
+                        match Material::load_library(&library, resolver) {
+                            Ok(library) => materials.extend(library),
+                            Err(ObjError::Io { .. }) => {}
+                            Err(e) => return Err(e),
+                        }
                     }
-                    ("vn", x) => {
-                        norms.push(RawVertex::new(x));
-                    }
-                    ("vt", x) => {
-                        text.push(RawVertex::new(x));
-                    }
-                    ("f ", x) => {
-                        faces.push(RawFace::new(x, invert_winding_order));
-                    }
-                    (_, _) => {}
-                };
-            }
+                    Ok(())
+                }
+                // A new object starts without a group.
+                "o" | "g" => {
+                    let (object, group) = match keyword {
+                        "o" => (x.trim().to_string(), String::new()),
+                        _ => (sub_mesh_names[sub_mesh].0.clone(), x.trim().to_string()),
+                    };
+                    let name = (object, group);
+                    sub_mesh = match sub_mesh_names.iter().position(|n| *n == name) {
+                        Some(existing) => existing,
+                        None => {
+                            sub_mesh_names.push(name);
+                            sub_mesh_names.len() - 1
+                        }
+                    };
+                    Ok(())
+                }
+                // Unknown materials fall back to the custom color, since
+                // many exporters reference placeholders like `None`.
+                "usemtl" => {
+                    material = materials.iter().rposition(|m| m.name == x.trim());
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+            parsed.map_err(|e| e.at(file_name, line_index + 1))?;
         }
-        Loader {
+        Ok(Loader {
             color,
             verts,
             norms,
             text,
             faces,
+            materials,
+            sub_mesh_names,
             invert_winding_order,
+            clockwise: false,
+            normal_weighting: NormalWeighting::default(),
+            crease_angle: None,
+        })
+    }
+
+    pub fn faces(&self) -> &[RawFace] {
+        &self.faces
+    }
+
+    /// Guesses the winding order of the faces in the file, see
+    /// [`Winding`]. Faces with `vn` data decide if there are any.
+    pub fn winding_order(&self) -> Option<Winding> {
+        let positions: Vec<Vec3> = self.verts.iter().map(|v| Vec3::from(v.vals)).collect();
+        let triangles: Vec<[usize; 3]> = self
+            .faces
+            .iter()
+            .map(|face| match self.invert_winding_order {
+                true => [face.verts[0], face.verts[2], face.verts[1]],
+                false => face.verts,
+            })
+            .collect();
+        let normals: Vec<Option<Vec3>> = self
+            .faces
+            .iter()
+            .map(|face| {
+                Some(
+                    face.norms?
+                        .iter()
+                        .map(|n| Vec3::from(self.norms[*n].vals))
+                        .sum(),
+                )
+            })
+            .collect();
+        winding::detect(&positions, &triangles, &normals)
+    }
+
+    /// Reverses the faces if needed, so that faces in the given winding
+    /// order come out the way the renderer draws them, with generated
+    /// normals pointing to their front. This replaces the
+    /// `invert_winding_order` argument of the constructor.
+    pub fn set_winding_order(&mut self, winding: Winding) {
+        let invert = winding.needs_invert();
+        self.clockwise = winding == Winding::Clockwise;
+        if invert == self.invert_winding_order {
+            return;
+        }
+        for face in &mut self.faces {
+            face.verts.swap(1, 2);
+            if let Some(norms) = &mut face.norms {
+                norms.swap(1, 2);
+            }
+            if let Some(text) = &mut face.text {
+                text.swap(1, 2);
+            }
+        }
+        self.invert_winding_order = invert;
+    }
+
+    /// The materials of every mtl library referenced by the file.
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// Sets how face normals are weighted when smooth normals are generated
+    /// for faces without `vn` data.
+    pub fn set_normal_weighting(&mut self, weighting: NormalWeighting) {
+        self.normal_weighting = weighting;
+    }
+
+    /// Sets the angle in radians above which neighbouring faces of the same
+    /// smoothing group are not smoothed together when normals are generated.
+    /// `None` smooths across every edge of a group.
+    pub fn set_crease_angle(&mut self, crease_angle: Option<f32>) {
+        self.crease_angle = crease_angle;
+    }
+
+    fn parse_smoothing(raw_arg: &str) -> Result<u32, ObjError> {
+        match raw_arg.trim() {
+            "off" => Ok(0),
+            x => x.parse().map_err(|_| ObjError::invalid_number(x)),
+        }
+    }
+
+    // Polygons are triangulated here rather than in `RawFace` because ear
+    // clipping needs the vertex positions.
+    fn parse_face(
+        raw_arg: &str,
+        invert: bool,
+        verts: &[RawVertex],
+        norms: usize,
+        text: usize,
+    ) -> Result<Vec<RawFace>, ObjError> {
+        let corners = RawFace::parse_corners(raw_arg, verts.len(), text, norms)?;
+        let positions: Vec<Vec3> = corners
+            .iter()
+            .map(|c| Vec3::from(verts[c.vert].vals))
+            .collect();
+        Ok(triangulate::triangulate(&positions)
+            .into_iter()
+            .map(|[a, b, c]| RawFace::new([corners[a], corners[b], corners[c]], invert))
+            .collect())
+    }
+
+    /// Returns the normal of each corner of every face. Normals from the
+    /// file are used where a face has them, the rest are generated.
+    fn face_normals(&self) -> Vec<[[f32; 3]; 3]> {
+        let missing: Vec<&RawFace> = self.faces.iter().filter(|f| f.norms.is_none()).collect();
+        let mut generated = if missing.is_empty() {
+            Vec::new()
+        } else {
+            let positions: Vec<Vec3> = self.verts.iter().map(|v| Vec3::from(v.vals)).collect();
+            let triangles: Vec<[usize; 3]> = missing.iter().map(|f| f.verts).collect();
+            let groups: Vec<u32> = missing.iter().map(|f| f.smoothing).collect();
+            normals::generate(
+                &positions,
+                &triangles,
+                &groups,
+                self.invert_winding_order != self.clockwise,
+                self.normal_weighting,
+                self.crease_angle,
+            )
         }
+        .into_iter();
+
+        self.faces
+            .iter()
+            .map(|face| match face.norms {
+                Some(normals) => normals.map(|n| self.norms[n].vals),
+                None => generated.next().unwrap().map(|n| n.to_array()),
+            })
+            .collect()
     }
 
+    /// Faces with a material are colored with its diffuse color, all other
+    /// faces with the custom color.
     pub fn as_normal_vertices(&self) -> Vec<NormalVertex> {
         let mut ret: Vec<NormalVertex> = Vec::new();
-        for face in &self.faces {
+        for (face, normals) in self.faces.iter().zip(self.face_normals()) {
             let verts = face.verts;
-            let normals = face.norms.unwrap();
+            let color = match face.material {
+                Some(m) => self.materials[m].diffuse,
+                None => self.color,
+            };
             ret.push(NormalVertex {
                 position: self.verts.get(verts[0]).unwrap().vals,
-                normal: self.norms.get(normals[0]).unwrap().vals,
-                color: self.color,
+                normal: normals[0],
+                color,
             });
             ret.push(NormalVertex {
                 position: self.verts.get(verts[1]).unwrap().vals,
-                normal: self.norms.get(normals[1]).unwrap().vals,
-                color: self.color,
+                normal: normals[1],
+                color,
             });
             ret.push(NormalVertex {
                 position: self.verts.get(verts[2]).unwrap().vals,
-                normal: self.norms.get(normals[2]).unwrap().vals,
-                color: self.color,
+                normal: normals[2],
+                color,
             });
         }
         ret
     }
+
+    /// Same as [`Loader::as_normal_vertices`], with the texture coordinates
+    /// of every corner instead of a color. Corners without a `vt` index get
+    /// `[0.0, 0.0]`.
+    pub fn as_textured_normal_vertices(&self) -> Vec<TexturedNormalVertex> {
+        self.as_normal_vertices()
+            .iter()
+            .zip(self.face_uvs())
+            .map(|(v, uv)| TexturedNormalVertex {
+                position: v.position,
+                normal: v.normal,
+                uv,
+            })
+            .collect()
+    }
+
+    // One entry per corner, in the same order as `as_normal_vertices`.
+    fn face_uvs(&self) -> impl Iterator<Item = [f32; 2]> + '_ {
+        self.faces.iter().flat_map(|face| match face.text {
+            Some(text) => text.map(|t| [self.text[t].vals[0], self.text[t].vals[1]]),
+            None => [[0.0, 0.0]; 3],
+        })
+    }
+
+    /// Same as [`Loader::as_normal_vertices`], with identical vertices merged
+    /// and referenced through an index buffer instead.
+    pub fn as_indexed_mesh(&self) -> IndexedMesh {
+        IndexedMesh::from_triangle_list(&self.as_normal_vertices())
+    }
+
+    /// Splits the mesh into one [`SubMesh`] per object or group and
+    /// material, in the order they first appear in the file. The faces are
+    /// reordered so that every sub-mesh covers one contiguous range of the
+    /// returned index buffer.
+    pub fn as_sub_meshes(&self) -> (IndexedMesh, Vec<SubMesh>) {
+        self.split_sub_meshes(&self.as_normal_vertices())
+    }
+
+    /// Same as [`Loader::as_sub_meshes`] with textured vertices.
+    pub fn as_textured_sub_meshes(&self) -> (IndexedMesh<TexturedNormalVertex>, Vec<SubMesh>) {
+        self.split_sub_meshes(&self.as_textured_normal_vertices())
+    }
+
+    // `vertices` holds one vertex per face corner.
+    fn split_sub_meshes<V: Pod>(&self, vertices: &[V]) -> (IndexedMesh<V>, Vec<SubMesh>) {
+        let mut keys: Vec<(usize, Option<usize>)> = Vec::new();
+        let mut order: Vec<(usize, usize)> = Vec::with_capacity(self.faces.len());
+        for (index, face) in self.faces.iter().enumerate() {
+            let key = (face.sub_mesh, face.material);
+            let position = match keys.iter().position(|k| *k == key) {
+                Some(position) => position,
+                None => {
+                    keys.push(key);
+                    keys.len() - 1
+                }
+            };
+            order.push((position, index));
+        }
+        order.sort_by_key(|(position, _)| *position);
+
+        let list: Vec<V> = order
+            .iter()
+            .flat_map(|(_, face)| vertices[face * 3..face * 3 + 3].iter().copied())
+            .collect();
+
+        let mut start = 0;
+        let sub_meshes = keys
+            .iter()
+            .enumerate()
+            .map(|(position, (names, material))| {
+                let faces = order.iter().filter(|(p, _)| *p == position).count() as u32;
+                let (object, group) = self.sub_mesh_names[*names].clone();
+                let indices = start..start + faces * 3;
+                start = indices.end;
+                SubMesh {
+                    object,
+                    group,
+                    material: *material,
+                    indices,
+                    transform: Mat4::IDENTITY,
+                    visible: true,
+                }
+            })
+            .collect();
+
+        (IndexedMesh::from_triangle_list(&list), sub_meshes)
+    }
 }
 
 /// A vertex type intended to be used to provide dummy rendering
@@ -243,6 +797,26 @@ pub struct NormalVertex {
 }
 vulkano::impl_vertex!(NormalVertex, position, normal, color);
 
+/// A vertex with texture coordinates, for textured models drawn with
+/// `RenderSystem::render_textured_model`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+pub struct TexturedNormalVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+vulkano::impl_vertex!(TexturedNormalVertex, position, normal, uv);
+
+// Lets `Model` deduplicate its vertices once while keeping both the colors
+// and the texture coordinates of every vertex.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct LoadedVertex {
+    vertex: NormalVertex,
+    uv: [f32; 2],
+}
+
 impl fmt::Display for DummyVertex {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let pos = format!("[{:.6}, {:.6}]", self.position[0], self.position[1]);
@@ -286,24 +860,45 @@ impl fmt::Display for NormalVertex {
     }
 }
 
+impl fmt::Display for TexturedNormalVertex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pos = format!(
+            "[{:.6}, {:.6}, {:.6}]",
+            self.position[0], self.position[1], self.position[2]
+        );
+        let norms = format!(
+            "[{:.6}, {:.6}, {:.6}]",
+            self.normal[0], self.normal[1], self.normal[2]
+        );
+        let uv = format!("[{:.6}, {:.6}]", self.uv[0], self.uv[1]);
+        write!(
+            f,
+            "TexturedNormalVertex {{ position: {}, normal: {}, uv: {} }}",
+            pos, norms, uv
+        )
+    }
+}
+
 /// Holds our data for a renderable model, including the model matrix data
 ///
-/// Note: When building an instance of `Model` the loader will assume that
-/// the input obj file is in clockwise winding order. If it is already in
-/// counter-clockwise winding order, call `.invert_winding_order(false)`
-/// when building the `Model`.
+/// Note: When building an instance of `Model` the loader detects the
+/// winding order of the input file, see [`ModelBuilder::winding_order`].
 pub struct Model {
-    data: Vec<NormalVertex>,
-    translation: Mat4,
-    rotation: Mat4,
-    uniform_scale: f32,
+    mesh: Arc<Mesh>,
+    // A copy of the sub-meshes of `mesh`, so that every model can show, hide
+    // and transform them on its own
+    sub_meshes: Vec<SubMesh>,
+    transform: Transform,
+    // Where the model is attached, e.g. by a `Scene`
+    parent: Mat4,
+    cull_mode: CullMode,
 
     // We might call multiple translation/rotation calls
     // in between asking for the model matrix. This lets us
     // only recreate the model matrices when needed.
     // Use a Cell with the interior mutability pattern,
     // so that it can be modified by methods that don't take &mut self
-    cache: Cell<Option<(ModelMatrices, NormalMatrices)>>,
+    cache: Cell<Option<(ModelMatrices, NormalMatrices, WorldBounds)>>,
 }
 
 #[derive(Copy, Clone)]
@@ -316,46 +911,323 @@ struct NormalMatrices {
     normal: Mat4,
 }
 
+// The bounding volumes of the mesh moved by the model matrix
+#[derive(Copy, Clone)]
+struct WorldBounds {
+    aabb: Aabb,
+    sphere: Sphere,
+}
+
+/// Where a [`ModelBuilder`] reads the obj data from.
+enum Source {
+    File(String),
+    Text(String),
+    Primitive(Primitive),
+}
+
+// A `Source` in a form that can be compared and hashed, for the
+// `MeshRegistry`. Primitives are kept as their `Debug` output, which
+// includes every parameter.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) enum SourceKey {
+    File(String),
+    Text(String),
+    Primitive(String),
+}
+
 pub struct ModelBuilder {
-    file_name: String,
+    source: Source,
+    // `None` reads from the file system with a `FileResolver`
+    resolver: Option<Box<dyn Resolver>>,
+    cache: bool,
+    write_cache: bool,
     custom_color: [f32; 3],
-    invert: bool,
+    // `None` detects the winding order of files
+    winding: Option<Winding>,
     scale_factor: f32,
+    cull_mode: CullMode,
+    normal_weighting: NormalWeighting,
+    crease_angle: Option<f32>,
+    up_axis: UpAxis,
+    recenter: Option<Recenter>,
+    normalize: Option<Normalize>,
 }
 
 impl ModelBuilder {
-    fn new(file: String) -> ModelBuilder {
+    fn new(source: Source) -> ModelBuilder {
         ModelBuilder {
-            file_name: file,
+            source,
+            resolver: None,
+            cache: true,
+            write_cache: false,
             custom_color: [1.0, 0.35, 0.137],
-            invert: true,
+            winding: None,
             scale_factor: 1.0,
+            cull_mode: CullMode::default(),
+            normal_weighting: NormalWeighting::default(),
+            crease_angle: None,
+            up_axis: UpAxis::default(),
+            recenter: None,
+            normalize: None,
         }
     }
 
-    pub fn build(self) -> Model {
-        let loader = Loader::new(self.file_name.as_str(), self.custom_color, self.invert);
-        Model {
-            data: loader.as_normal_vertices(),
-            translation: Mat4::IDENTITY,
-            rotation: Mat4::IDENTITY,
-            uniform_scale: self.scale_factor,
-            cache: Cell::new(None),
+    pub fn build(self) -> Result<Model, ObjError> {
+        let scale_factor = self.scale_factor;
+        let cull_mode = self.cull_mode;
+        let mut model = Model::from_mesh(Arc::new(self.build_mesh()?));
+        model.transform.scale = Vec3::splat(scale_factor);
+        model.cull_mode = cull_mode;
+        Ok(model)
+    }
+
+    /// Loads only the geometry and materials, which can be shared by many
+    /// models through [`Model::from_mesh`]. The scale factor is ignored.
+    pub fn build_mesh(self) -> Result<Mesh, ObjError> {
+        // Only files read through the file system have a modification time
+        // to compare the cache against.
+        let cache_file = match &self.source {
+            Source::File(file_name) if self.cache && self.resolver.is_none() => {
+                Some((file_name, Model::cache_path(file_name)))
+            }
+            _ => None,
+        };
+        let options = self.options_fingerprint();
+        let cached = cache_file
+            .as_ref()
+            .filter(|(file_name, cache_file)| is_newer(cache_file, file_name))
+            .and_then(|(_, cache_file)| cache::read(cache_file).ok())
+            .filter(|(cached_options, _)| *cached_options == options);
+        match cached {
+            Some((_, mesh)) => Ok(mesh),
+            None => {
+                let mut mesh = self.parse()?;
+                placement::apply(&mut mesh, self.up_axis, self.recenter, self.normalize);
+                // The cache is only an optimization, so failing to write it,
+                // e.g. in a read-only directory, is not an error.
+                if let Some((_, cache_file)) = cache_file.filter(|_| self.write_cache) {
+                    let _ = cache::write(&mesh, options, &cache_file);
+                }
+                Ok(mesh)
+            }
+        }
+    }
+
+    fn parse(&self) -> Result<Mesh, ObjError> {
+        let resolver = self.resolver.as_deref().unwrap_or(&FileResolver);
+        let mut loader = match &self.source {
+            Source::File(file_name) => {
+                let input = resolver.open(file_name).map_err(|source| ObjError::Io {
+                    path: file_name.clone(),
+                    source,
+                })?;
+                match extension(file_name).as_str() {
+                    "ply" => {
+                        let mut loader = PlyLoader::from_reader(
+                            input,
+                            file_name,
+                            self.custom_color,
+                            self.invert(),
+                        )?;
+                        if let Some(winding) = self.winding.or_else(|| loader.winding_order()) {
+                            loader.set_winding_order(winding);
+                        }
+                        loader.set_normal_weighting(self.normal_weighting);
+                        loader.set_crease_angle(self.crease_angle);
+                        let vertices = loader.as_normal_vertices();
+                        return Ok(single_mesh("", &vertices, &loader.corner_uvs()));
+                    }
+                    "gltf" | "glb" => {
+                        let mut bytes = Vec::new();
+                        let mut input = input;
+                        input
+                            .read_to_end(&mut bytes)
+                            .map_err(|source| ObjError::Io {
+                                path: file_name.clone(),
+                                source,
+                            })?;
+                        // External buffers are read from the file system,
+                        // next to the file.
+                        let base = std::path::Path::new(file_name).parent();
+                        let scene =
+                            GltfScene::from_slice(&bytes, base).map_err(|e| e.at(file_name, 0))?;
+                        return Ok(scene.flattened(self.invert()));
+                    }
+                    "stl" => {
+                        let mut loader = StlLoader::from_reader(
+                            input,
+                            file_name,
+                            self.custom_color,
+                            self.invert(),
+                        )?;
+                        if let Some(winding) = self.winding.or_else(|| loader.winding_order()) {
+                            loader.set_winding_order(winding);
+                        }
+                        let vertices = loader.as_normal_vertices();
+                        let uvs = vec![[0.0, 0.0]; vertices.len()];
+                        return Ok(single_mesh(loader.name(), &vertices, &uvs));
+                    }
+                    _ => Loader::from_reader(
+                        input,
+                        file_name,
+                        resolver,
+                        self.custom_color,
+                        self.invert(),
+                    )?,
+                }
+            }
+            Source::Primitive(primitive) => {
+                return Ok(primitive.mesh(self.custom_color, self.invert()))
+            }
+            Source::Text(text) => Loader::from_reader(
+                text.as_bytes(),
+                "<string>",
+                resolver,
+                self.custom_color,
+                self.invert(),
+            )?,
+        };
+        if let Some(winding) = self.winding.or_else(|| loader.winding_order()) {
+            loader.set_winding_order(winding);
+        }
+        loader.set_normal_weighting(self.normal_weighting);
+        loader.set_crease_angle(self.crease_angle);
+        let loaded: Vec<LoadedVertex> = loader
+            .as_normal_vertices()
+            .into_iter()
+            .zip(loader.face_uvs())
+            .map(|(vertex, uv)| LoadedVertex { vertex, uv })
+            .collect();
+        let (loaded, sub_meshes) = loader.split_sub_meshes(&loaded);
+        let vertices: Vec<NormalVertex> = loaded.vertices.iter().map(|v| v.vertex).collect();
+        let (bounds, sphere) = mesh::bounds_of(&vertices);
+        Ok(Mesh {
+            bounds,
+            sphere,
+            geometry: IndexedMesh {
+                vertices,
+                indices: loaded.indices,
+            },
+            uvs: loaded.vertices.iter().map(|v| v.uv).collect(),
+            tangents: Vec::new(),
+            sub_meshes,
+            materials: loader.materials().to_vec(),
+            generation: 0,
+            id: mesh::MeshId::new(),
+        })
+    }
+
+    // Whether faces are reversed before the winding order is detected
+    fn invert(&self) -> bool {
+        self.winding.is_none_or(Winding::needs_invert)
+    }
+
+    // Identifies the mesh this builder loads for the `MeshRegistry`. The
+    // whole source and options are part of the key, so that different
+    // meshes can't share one.
+    fn registry_key(&self) -> Option<(SourceKey, Vec<u8>)> {
+        if self.resolver.is_some() {
+            return None;
+        }
+        let source = match &self.source {
+            Source::File(file_name) => SourceKey::File(file_name.clone()),
+            Source::Text(text) => SourceKey::Text(text.clone()),
+            Source::Primitive(primitive) => SourceKey::Primitive(format!("{:?}", primitive)),
+        };
+        Some((source, self.options()))
+    }
+
+    // Identifies the options that change the processed geometry, so that a
+    // cache written with other options is not picked up.
+    fn options_fingerprint(&self) -> u64 {
+        cache::fnv1a(&self.options())
+    }
+
+    // The options that change the processed geometry, as bytes.
+    fn options(&self) -> Vec<u8> {
+        let mut options: Vec<u8> = Vec::new();
+        for c in self.custom_color {
+            options.extend_from_slice(&c.to_le_bytes());
+        }
+        options.push(match self.winding {
+            None => 0,
+            Some(Winding::CounterClockwise) => 1,
+            Some(Winding::Clockwise) => 2,
+        });
+        options.push(match self.normal_weighting {
+            NormalWeighting::Area => 0,
+            NormalWeighting::Angle => 1,
+        });
+        if let Some(angle) = self.crease_angle {
+            options.extend_from_slice(&angle.to_le_bytes());
         }
+        options.push(self.up_axis as u8);
+        options.push(self.recenter.map_or(0, |r| r as u8 + 1));
+        options.push(self.normalize.map_or(0, |n| n as u8 + 1));
+        options
     }
 
+    /// The color of faces that have no material.
     pub fn color(mut self, new_color: [f32; 3]) -> ModelBuilder {
         self.custom_color = new_color;
         self
     }
 
     pub fn file(mut self, file: String) -> ModelBuilder {
-        self.file_name = file;
+        self.source = Source::File(file);
+        self
+    }
+
+    /// Opens the model file and the mtl libraries it references, see
+    /// [`Resolver`]. Defaults to [`FileResolver`].
+    pub fn resolver(mut self, resolver: impl Resolver + 'static) -> ModelBuilder {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Whether to read a binary cache next to the obj file, see
+    /// [`Model::cache_path`]. The cache is used when it is newer than the obj
+    /// file and was made with the same options. Only the main file is
+    /// checked: edits to mtl libraries, or to the external buffers and
+    /// images of a glTF file, don't invalidate it. Defaults to true, but is
+    /// skipped for in-memory sources and custom resolvers.
+    pub fn cache(mut self, enabled: bool) -> ModelBuilder {
+        self.cache = enabled;
+        self
+    }
+
+    /// Whether to write the cache read by [`ModelBuilder::cache`] when it
+    /// can't be used, so that the next load is faster. Failing to write it
+    /// doesn't fail the load. Defaults to false, so loading a model leaves
+    /// the directory it is in untouched.
+    pub fn write_cache(mut self, enabled: bool) -> ModelBuilder {
+        self.write_cache = enabled;
+        self
+    }
+
+    /// The winding order of the file, which is otherwise detected from
+    /// its normals, or from the volume of closed meshes without normals.
+    /// Open meshes without normals are assumed to be counter-clockwise.
+    /// Primitives are always counter-clockwise unless set.
+    pub fn winding_order(mut self, winding: Winding) -> ModelBuilder {
+        self.winding = Some(winding);
         self
     }
 
+    /// Same as [`ModelBuilder::winding_order`] with
+    /// [`Winding::CounterClockwise`] if `invert` is set and
+    /// [`Winding::Clockwise`] otherwise.
     pub fn invert_winding_order(mut self, invert: bool) -> ModelBuilder {
-        self.invert = invert;
+        self.winding = Some(match invert {
+            true => Winding::CounterClockwise,
+            false => Winding::Clockwise,
+        });
+        self
+    }
+
+    /// See [`Model::set_cull_mode`].
+    pub fn cull_mode(mut self, cull_mode: CullMode) -> ModelBuilder {
+        self.cull_mode = cull_mode;
         self
     }
 
@@ -363,20 +1235,286 @@ impl ModelBuilder {
         self.scale_factor = scale;
         self
     }
+
+    /// Only affects models without `vn` data, see [`NormalWeighting`].
+    pub fn normal_weighting(mut self, weighting: NormalWeighting) -> ModelBuilder {
+        self.normal_weighting = weighting;
+        self
+    }
+
+    /// Keeps edges sharper than `radians` hard when normals are generated
+    /// for models without `vn` data.
+    pub fn crease_angle(mut self, radians: f32) -> ModelBuilder {
+        self.crease_angle = Some(radians);
+        self
+    }
+
+    /// The up-axis of the file. Z-up models are rotated to be Y-up.
+    /// Defaults to [`UpAxis::Y`].
+    pub fn up_axis(mut self, up_axis: UpAxis) -> ModelBuilder {
+        self.up_axis = up_axis;
+        self
+    }
+
+    /// Moves the geometry so that `center` ends up at the origin of model
+    /// space, after the up-axis is converted.
+    pub fn recenter(mut self, center: Recenter) -> ModelBuilder {
+        self.recenter = Some(center);
+        self
+    }
+
+    /// Scales the geometry to a unit size, after it is recentered. The
+    /// scale is baked into the vertices, so
+    /// [`ModelBuilder::uniform_scale_factor`] still applies on top of it.
+    pub fn normalize(mut self, size: Normalize) -> ModelBuilder {
+        self.normalize = Some(size);
+        self
+    }
 }
 
 impl Model {
+    /// Loads a model from a file. Files ending in `.ply` are read with
+    /// [`PlyLoader`], files ending in `.stl` with [`StlLoader`], and
+    /// everything else as obj.
+    ///
+    /// Files ending in `.gltf` or `.glb` are read with [`GltfScene`], and
+    /// all of their meshes are merged into one, placed by their nodes. Use
+    /// [`GltfScene::models`] instead to keep them apart.
     pub fn builder(file_name: &str) -> ModelBuilder {
-        ModelBuilder::new(file_name.into())
+        ModelBuilder::new(Source::File(file_name.into()))
+    }
+
+    /// Builds a model from obj data held in memory. Errors are reported
+    /// against the file name `<string>`, and mtl libraries are opened
+    /// relative to the working directory unless another
+    /// [`ModelBuilder::resolver`] is set.
+    pub fn from_obj_str(obj: &str) -> ModelBuilder {
+        ModelBuilder::new(Source::Text(obj.into()))
+    }
+
+    /// Builds a model from a generated shape instead of a file. The color,
+    /// winding order and placement options of the builder apply as usual.
+    pub fn primitive(primitive: Primitive) -> ModelBuilder {
+        ModelBuilder::new(Source::Primitive(primitive))
+    }
+
+    /// Where [`ModelBuilder`] caches the processed geometry of `file_name`.
+    pub fn cache_path(file_name: &str) -> String {
+        format!("{}.mesh", file_name)
+    }
+
+    /// Writes the processed geometry and materials to a binary mesh file,
+    /// which [`Model::load`] reads much faster than the obj file it came
+    /// from. Transforms and sub-mesh visibility are not saved.
+    pub fn save(&self, path: &str) -> Result<(), ObjError> {
+        cache::write(&self.mesh, 0, path)
+    }
+
+    /// Reads a binary mesh file written by [`Model::save`] or by the cache of
+    /// [`ModelBuilder`]. Files with the wrong magic, version or checksum are
+    /// rejected with [`ObjError::InvalidCache`].
+    pub fn load(path: &str) -> Result<Model, ObjError> {
+        cache::read(path).map(|(_, mesh)| Model::from_mesh(Arc::new(mesh)))
+    }
+
+    /// Writes the model to an obj file, see [`ObjWriter`].
+    pub fn obj_writer(&self) -> ObjWriter<'_> {
+        ObjWriter::with_matrices(&self.mesh, (self.model_matrix(), self.normal_matrix()))
     }
 
+    /// A model drawing `mesh`, without transforms. Models made from the same
+    /// mesh share its geometry.
+    pub fn from_mesh(mesh: Arc<Mesh>) -> Model {
+        Model {
+            sub_meshes: mesh.sub_meshes.clone(),
+            mesh,
+            transform: Transform::IDENTITY,
+            parent: Mat4::IDENTITY,
+            cull_mode: CullMode::default(),
+            cache: Cell::new(None),
+        }
+    }
+
+    /// The mesh this model draws.
+    pub fn shared_mesh(&self) -> &Arc<Mesh> {
+        &self.mesh
+    }
+
+    /// The model as a non-indexed triangle list.
     pub fn data(&self) -> Vec<NormalVertex> {
-        self.data.clone()
+        self.mesh.geometry.to_triangle_list()
+    }
+
+    /// The bounding box of the model in model space.
+    pub fn bounds(&self) -> Aabb {
+        self.mesh.bounds
+    }
+
+    /// The bounding sphere of the model in model space.
+    pub fn bounding_sphere(&self) -> Sphere {
+        self.mesh.sphere
+    }
+
+    /// The bounding box of the model in world space, after translation,
+    /// rotation and scale. Sub-mesh transforms are not included.
+    pub fn world_bounds(&self) -> Aabb {
+        self.cached().2.aabb
+    }
+
+    /// The bounding sphere of the model in world space, see
+    /// [`Model::world_bounds`].
+    pub fn world_bounding_sphere(&self) -> Sphere {
+        self.cached().2.sphere
+    }
+
+    /// The closest triangle of the visible sub-meshes that `ray`, given in
+    /// world space, crosses from either side.
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        let geometry = &self.mesh.geometry;
+        let mut closest: Option<RayHit> = None;
+        for sub_mesh in self.sub_meshes.iter().filter(|s| s.visible) {
+            let (model, normals) = self.sub_mesh_matrices(sub_mesh);
+            // Distances along the ray are the same in model space.
+            let local = ray.transformed(model.inverse());
+            if sub_mesh.transform == Mat4::IDENTITY
+                && local.intersect_aabb(&self.mesh.bounds).is_none()
+            {
+                continue;
+            }
+            let triangles = sub_mesh.indices.start as usize / 3..sub_mesh.indices.end as usize / 3;
+            for triangle in triangles {
+                let corners = [0, 1, 2].map(|i| geometry.indices.get(triangle * 3 + i).unwrap());
+                let [a, b, c] = corners.map(|v| Vec3::from(geometry.vertices[v].position));
+                let (distance, barycentric) = match local.intersect_triangle(a, b, c) {
+                    Some(hit) => hit,
+                    None => continue,
+                };
+                if closest.is_some_and(|hit| hit.distance <= distance) {
+                    continue;
+                }
+                let normal: Vec3 = corners
+                    .iter()
+                    .zip(barycentric.to_array())
+                    .map(|(v, weight)| Vec3::from(geometry.vertices[*v].normal) * weight)
+                    .sum();
+                closest = Some(RayHit {
+                    distance,
+                    position: ray.at(distance),
+                    triangle,
+                    barycentric,
+                    normal: normals.transform_vector3(normal).normalize_or_zero(),
+                });
+            }
+        }
+        closest
+    }
+
+    /// The model as deduplicated vertices and indices, see [`IndexedMesh`].
+    pub fn mesh(&self) -> &IndexedMesh {
+        &self.mesh.geometry
+    }
+
+    /// Changes the geometry of the model. A mesh shared with other models is
+    /// copied first, so that they keep the old geometry.
+    ///
+    /// Added vertices get the texture coordinates `[0.0, 0.0]` and, if the
+    /// mesh has tangents, the tangent `+X`. The index
+    /// ranges of the sub-meshes are cut to the new number of indices.
+    pub fn edit_mesh(&mut self, edit: impl FnOnce(&mut IndexedMesh)) {
+        let mesh = Arc::make_mut(&mut self.mesh);
+        edit(&mut mesh.geometry);
+        let vertices = &mesh.geometry.vertices;
+        mesh.uvs.resize(vertices.len(), [0.0, 0.0]);
+        if !mesh.tangents.is_empty() {
+            mesh.tangents.resize(vertices.len(), [1.0, 0.0, 0.0, 1.0]);
+        }
+        (mesh.bounds, mesh.sphere) = mesh::bounds_of(vertices);
+        let index_count = mesh.geometry.indices.len() as u32;
+        for sub_mesh in mesh.sub_meshes.iter_mut().chain(&mut self.sub_meshes) {
+            sub_mesh.indices.start = sub_mesh.indices.start.min(index_count);
+            sub_mesh.indices.end = sub_mesh.indices.end.min(index_count);
+        }
+        mesh.generation += 1;
+        self.cache.set(None);
+    }
+
+    /// Checks the mesh for broken geometry, see [`Mesh::validate`].
+    pub fn validate(&self) -> ValidationReport {
+        self.mesh.validate()
+    }
+
+    /// Fixes the problems [`Model::validate`] reports, as far as enabled in
+    /// `repair`. Like [`Model::edit_mesh`], a shared mesh is copied first.
+    /// Sub-meshes keep their transform and visibility.
+    pub fn repair(&mut self, repair: Repair) -> RepairReport {
+        let mesh = Arc::make_mut(&mut self.mesh);
+        let report = mesh.repair(repair);
+        for (sub_mesh, repaired) in self.sub_meshes.iter_mut().zip(&mesh.sub_meshes) {
+            sub_mesh.indices = repaired.indices.clone();
+        }
+        self.cache.set(None);
+        report
+    }
+
+    /// The vertices of [`Model::mesh`] with texture coordinates instead of
+    /// colors. They share the index buffer of [`Model::mesh`].
+    pub fn textured_vertices(&self) -> Vec<TexturedNormalVertex> {
+        self.mesh.textured_vertices()
+    }
+
+    pub fn materials(&self) -> &[Material] {
+        &self.mesh.materials
+    }
+
+    /// The named parts of the model. Together they cover the whole index
+    /// buffer of [`Model::mesh`].
+    pub fn sub_meshes(&self) -> &[SubMesh] {
+        &self.sub_meshes
+    }
+
+    /// Which faces the renderer skips. Defaults to [`CullMode::Back`].
+    pub fn cull_mode(&self) -> CullMode {
+        self.cull_mode
+    }
+
+    /// Draws both sides of open or double-sided meshes with
+    /// [`CullMode::None`].
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        self.cull_mode = cull_mode;
+    }
+
+    /// Gives access to the transform and visibility of every part.
+    pub fn sub_meshes_mut(&mut self) -> &mut [SubMesh] {
+        &mut self.sub_meshes
+    }
+
+    /// Shows or hides every sub-mesh whose object or group is `name`.
+    pub fn set_visible(&mut self, name: &str, visible: bool) {
+        for sub_mesh in self.sub_meshes.iter_mut().filter(|s| s.has_name(name)) {
+            sub_mesh.visible = visible;
+        }
+    }
+
+    /// Applies `transform` in model space to every sub-mesh whose object or
+    /// group is `name`, replacing its previous transform.
+    pub fn set_sub_mesh_transform(&mut self, name: &str, transform: Mat4) {
+        for sub_mesh in self.sub_meshes.iter_mut().filter(|s| s.has_name(name)) {
+            sub_mesh.transform = transform;
+        }
+    }
+
+    /// The model and normal matrices of one part of the model.
+    pub fn sub_mesh_matrices(&self, sub_mesh: &SubMesh) -> (Mat4, Mat4) {
+        if sub_mesh.transform == Mat4::IDENTITY {
+            return (self.model_matrix(), self.normal_matrix());
+        }
+        let model = self.model_matrix() * sub_mesh.transform;
+        (model, model.inverse().transpose())
     }
 
     pub fn color_data(&self) -> Vec<ColoredVertex> {
         let mut ret: Vec<ColoredVertex> = Vec::new();
-        for v in &self.data {
+        for v in &self.data() {
             ret.push(ColoredVertex {
                 position: v.position,
                 color: v.color,
@@ -386,50 +1524,585 @@ impl Model {
     }
 
     pub fn model_matrix(&self) -> Mat4 {
+        self.cached().0.model
+    }
+
+    pub fn normal_matrix(&self) -> Mat4 {
+        self.cached().1.normal
+    }
+
+    fn cached(&self) -> (ModelMatrices, NormalMatrices, WorldBounds) {
         if let Some(cache) = self.cache.get() {
-            return cache.0.model;
+            return cache;
         }
 
         // recalculate matrix
-        let model = self.translation * self.rotation;
-        let model = model * Mat4::from_scale([self.uniform_scale; 3].into());
+        let model = self.parent * self.transform.matrix();
         let normal = model.inverse().transpose();
+        let bounds = WorldBounds {
+            aabb: self.mesh.bounds.transformed(model),
+            sphere: self.mesh.sphere.transformed(model),
+        };
 
-        self.cache
-            .set(Some((ModelMatrices { model }, NormalMatrices { normal })));
+        let cache = (ModelMatrices { model }, NormalMatrices { normal }, bounds);
+        self.cache.set(Some(cache));
+        cache
+    }
 
-        model
+    /// The translation, rotation and scale that make up
+    /// [`Model::model_matrix`].
+    pub fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    pub fn normal_matrix(&self) -> Mat4 {
-        if let Some(cache) = self.cache.get() {
-            return cache.1.normal;
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.cache.set(None);
+    }
+
+    /// Changes the transform in place.
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        // Nothing can read the cache while the transform is borrowed.
+        self.cache.set(None);
+        &mut self.transform
+    }
+
+    /// The world matrix of whatever the model is attached to, applied
+    /// after [`Model::transform`]. [`crate::scene::Scene`] sets it for the
+    /// models of its nodes.
+    pub fn parent_matrix(&self) -> Mat4 {
+        self.parent
+    }
+
+    pub fn set_parent_matrix(&mut self, parent: Mat4) {
+        if parent != self.parent {
+            self.parent = parent;
+            self.cache.set(None);
         }
+    }
 
-        // recalculate matrix
-        let model = self.translation * self.rotation;
-        let model = model * Mat4::from_scale([self.uniform_scale; 3].into());
-        let normal = model.inverse().transpose();
+    pub fn translation(&self) -> Vec3 {
+        self.transform.translation
+    }
+
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.transform_mut().translation = translation;
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.transform.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.transform_mut().rotation = rotation;
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        self.transform.scale
+    }
 
-        self.cache
-            .set(Some((ModelMatrices { model }, NormalMatrices { normal })));
+    /// Replaces the scale, including the one set by
+    /// [`ModelBuilder::uniform_scale_factor`].
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.transform_mut().scale = scale;
+    }
 
-        normal
+    /// Turns the model so that its -Z axis points at `target`, see
+    /// [`Transform::look_at`].
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        self.transform_mut().look_at(target, up);
     }
 
+    /// Adds a rotation of `radians` around the axis `v` in model space, on
+    /// top of the current rotation.
     pub fn rotate(&mut self, radians: f32, v: Vec3) {
-        self.rotation *= Mat4::from_axis_angle(v, radians);
-        self.cache.set(None);
+        let rotation = self.transform.rotation * Quat::from_axis_angle(v, radians);
+        self.set_rotation(rotation);
     }
 
+    /// Moves the model by `v` in world space.
     pub fn translate(&mut self, v: Vec3) {
-        self.translation *= Mat4::from_translation(v);
-        self.cache.set(None);
+        self.transform_mut().translation += v;
     }
 
     /// Return the model's rotation to 0
     pub fn zero_rotation(&mut self) {
-        self.rotation = Mat4::IDENTITY;
-        self.cache.set(None);
+        self.set_rotation(Quat::IDENTITY);
+    }
+}
+
+// Whether `path` was modified after `than`. Missing files or file systems
+// without modification times never count as newer.
+fn is_newer(path: &str, than: &str) -> bool {
+    let modified = |p: &str| std::fs::metadata(p).and_then(|m| m.modified());
+    match (modified(path), modified(than)) {
+        (Ok(path), Ok(than)) => path > than,
+        _ => false,
+    }
+}
+
+// The lowercase extension of `file_name`, which picks the file format.
+fn extension(file_name: &str) -> String {
+    std::path::Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+// A mesh with a single sub-mesh and no materials, from one vertex and one
+// texture coordinate per triangle corner.
+fn single_mesh(object: &str, corners: &[NormalVertex], uvs: &[[f32; 2]]) -> Mesh {
+    let loaded: Vec<LoadedVertex> = corners
+        .iter()
+        .zip(uvs)
+        .map(|(vertex, uv)| LoadedVertex {
+            vertex: *vertex,
+            uv: *uv,
+        })
+        .collect();
+    let loaded = IndexedMesh::from_triangle_list(&loaded);
+    let vertices: Vec<NormalVertex> = loaded.vertices.iter().map(|v| v.vertex).collect();
+    let (bounds, sphere) = mesh::bounds_of(&vertices);
+    Mesh {
+        bounds,
+        sphere,
+        sub_meshes: vec![SubMesh {
+            object: object.to_string(),
+            group: String::new(),
+            material: None,
+            indices: 0..loaded.indices.len() as u32,
+            transform: Mat4::IDENTITY,
+            visible: true,
+        }],
+        geometry: IndexedMesh {
+            vertices,
+            indices: loaded.indices,
+        },
+        uvs: loaded.vertices.iter().map(|v| v.uv).collect(),
+        tangents: Vec::new(),
+        materials: Vec::new(),
+        generation: 0,
+        id: mesh::MeshId::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead, Read};
+
+    use glam::Vec3;
+
+    use super::*;
+
+    const COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+    fn load(obj: &str) -> Result<Loader, ObjError> {
+        Loader::from_reader(obj.as_bytes(), "test.obj", &FileResolver, COLOR, false)
+    }
+
+    fn vertex_normal(vertices: &[NormalVertex], position: [f32; 3]) -> Vec<Vec3> {
+        vertices
+            .iter()
+            .filter(|v| v.position == position)
+            .map(|v| Vec3::from(v.normal))
+            .collect()
+    }
+
+    #[test]
+    fn vertices_take_one_to_three_components_and_ignore_the_rest() {
+        assert_eq!(RawVertex::new("0.5").unwrap().vals, [0.5, 0.0, 0.0]);
+        assert_eq!(RawVertex::new("0.5 0.25").unwrap().vals, [0.5, 0.25, 0.0]);
+        assert_eq!(RawVertex::new("1 2 3").unwrap().vals, [1.0, 2.0, 3.0]);
+        // The weight of rational curves
+        assert_eq!(RawVertex::new("1 2 3 1").unwrap().vals, [1.0, 2.0, 3.0]);
+        // Vertex colors written by MeshLab and ZBrush
+        assert_eq!(
+            RawVertex::new("1 2 3 0.5 0.5 1").unwrap().vals,
+            [1.0, 2.0, 3.0]
+        );
+        assert!(matches!(
+            RawVertex::new("  "),
+            Err(ObjError::Malformed { .. })
+        ));
+        assert!(matches!(
+            RawVertex::new("1 x 3"),
+            Err(ObjError::InvalidNumber { token, .. }) if token == "x"
+        ));
+
+        let obj = "v 0 0 0 1\nv 1 0 0 1 0 0\nv 0 1 0 0 1 0\nvt 0\nvt 1\nvt 0.5\n\
+                   vn 0 0 1\nf 1/1/1 2/2/1 3/3/1\n";
+        let loader = load(obj).unwrap();
+        assert_eq!(loader.faces().len(), 1);
+        assert_eq!(loader.verts[1].vals, [1.0, 0.0, 0.0]);
+        assert_eq!(loader.text[2].vals, [0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let loader = load(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\n\
+             f -3//-1 -2//-1 -1//-1\n\
+             v 0 0 1\nf -4//1 -3//1 -1//1\n",
+        )
+        .unwrap();
+        let faces = loader.faces();
+        assert_eq!(faces[0].verts, [0, 1, 2]);
+        assert_eq!(faces[0].norms, Some([0, 0, 0]));
+        assert_eq!(faces[1].verts, [0, 1, 3]);
+    }
+
+    #[test]
+    fn zero_and_out_of_range_indices_are_errors() {
+        let error = load("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n").err().unwrap();
+        assert!(matches!(error, ObjError::IndexOutOfRange { line: 4, .. }));
+        let error = load("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 1 2\n").err().unwrap();
+        assert!(matches!(error, ObjError::IndexOutOfRange { line: 4, .. }));
+        let error = load("v 0 0 0\nv 1 0 0\nf 1 2 3\nv 0 1 0\n").err().unwrap();
+        assert!(matches!(error, ObjError::IndexOutOfRange { line: 3, .. }));
+    }
+
+    #[test]
+    fn mtllib_is_opened_through_a_closure_resolver() {
+        let resolver = |path: &str| -> io::Result<Box<dyn BufRead>> {
+            match path {
+                "colors.mtl" => Ok(Box::new(
+                    "newmtl red\nKd 1 0 0\nmap_Kd red.png\n".as_bytes(),
+                )),
+                "red.png" => Ok(Box::new(&b"\x89PNG"[..])),
+                _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        };
+        let obj = "mtllib colors.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   usemtl red\nf 1 2 3\nusemtl None\nf 1 3 2\n";
+        let loader =
+            Loader::from_reader(obj.as_bytes(), "model.obj", &resolver, COLOR, false).unwrap();
+        assert_eq!(loader.materials().len(), 1);
+        let material = &loader.materials()[0];
+        assert_eq!(material.name, "red");
+        assert_eq!(material.library, "colors.mtl");
+        assert_eq!(material.diffuse_map.as_deref(), Some("red.png"));
+        let mut texture = Vec::new();
+        material
+            .open_map("red.png", &resolver)
+            .unwrap()
+            .read_to_end(&mut texture)
+            .unwrap();
+        assert_eq!(texture, b"\x89PNG");
+        let vertices = loader.as_normal_vertices();
+        assert_eq!(vertices[0].color, [1.0, 0.0, 0.0]);
+        assert_eq!(vertices[3].color, COLOR);
+
+        let model = Model::from_obj_str(obj).resolver(resolver).build().unwrap();
+        assert_eq!(model.materials()[0].diffuse, [1.0, 0.0, 0.0]);
+
+        // Missing libraries are skipped, keeping the geometry.
+        let obj = "mtllib missing.mtl colors.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   usemtl red\nf 1 2 3\nusemtl blue\nf 1 3 2\n";
+        let loader =
+            Loader::from_reader(obj.as_bytes(), "model.obj", &resolver, COLOR, false).unwrap();
+        assert_eq!(loader.materials().len(), 1);
+        let vertices = loader.as_normal_vertices();
+        assert_eq!(vertices[0].color, [1.0, 0.0, 0.0]);
+        assert_eq!(vertices[3].color, COLOR);
+    }
+
+    #[test]
+    fn texture_maps_are_resolved_against_their_library() {
+        // Every file lives in an archive, with names relative to the file
+        // that refers to them.
+        struct Archive;
+        impl Resolver for Archive {
+            fn resolve(&self, referenced_by: &str, path: &str) -> String {
+                match referenced_by.rsplit_once('/') {
+                    Some((dir, _)) => format!("{dir}/{path}"),
+                    None => path.to_string(),
+                }
+            }
+
+            fn open(&self, path: &str) -> io::Result<Box<dyn BufRead>> {
+                match path {
+                    "assets/mtl/colors.mtl" => {
+                        Ok(Box::new("newmtl red\nmap_Kd textures/red.png\n".as_bytes()))
+                    }
+                    "assets/mtl/textures/red.png" => Ok(Box::new(&b"red"[..])),
+                    _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+                }
+            }
+        }
+
+        let obj = "mtllib mtl/colors.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+        let loader =
+            Loader::from_reader(obj.as_bytes(), "assets/model.obj", &Archive, COLOR, false)
+                .unwrap();
+        let material = &loader.materials()[0];
+        assert_eq!(material.library, "assets/mtl/colors.mtl");
+        let map = material.diffuse_map.as_deref().unwrap();
+        assert_eq!(
+            material.resolve_map(map, &Archive),
+            "assets/mtl/textures/red.png"
+        );
+        let mut texture = String::new();
+        material
+            .open_map(map, &Archive)
+            .unwrap()
+            .read_to_string(&mut texture)
+            .unwrap();
+        assert_eq!(texture, "red");
+        let error = material.open_map("missing.png", &Archive).err().unwrap();
+        assert!(matches!(error, ObjError::Io { path, .. } if path == "assets/mtl/missing.png"));
+    }
+
+    #[test]
+    fn raycast_distances_are_in_world_units() {
+        let mut model = Model::primitive(Primitive::Cube { divisions: 1 })
+            .build()
+            .unwrap();
+        model.set_translation(Vec3::new(0.0, 0.0, -10.0));
+        model.set_scale(Vec3::splat(2.0));
+
+        // The cube spans -12 to -8 along Z.
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 0.0), -Vec3::Z);
+        let hit = model.raycast(&ray).unwrap();
+        assert!((hit.distance - 8.0).abs() < 1e-5, "{}", hit.distance);
+        assert!(hit.position.abs_diff_eq(Vec3::new(0.5, 0.5, -8.0), 1e-5));
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-5));
+        let corners = [0, 1, 2].map(|i| model.mesh().indices.get(hit.triangle * 3 + i).unwrap());
+        let point: Vec3 = corners
+            .iter()
+            .zip(hit.barycentric.to_array())
+            .map(|(v, weight)| Vec3::from(model.mesh().vertices[*v].position) * weight)
+            .sum();
+        assert!(model
+            .model_matrix()
+            .transform_point3(point)
+            .abs_diff_eq(hit.position, 1e-5));
+
+        // Outside of the scaled cube, and pointing away from it
+        assert!(model
+            .raycast(&Ray::new(Vec3::new(2.5, 0.0, 0.0), -Vec3::Z))
+            .is_none());
+        assert!(model.raycast(&Ray::new(Vec3::ZERO, Vec3::Z)).is_none());
+
+        model.set_visible("cube", false);
+        assert!(model.raycast(&ray).is_none());
+    }
+
+    #[test]
+    fn smoothing_groups_split_generated_normals() {
+        // A floor and a wall meeting along the X axis
+        let geometry = "v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nv 0 1 0\nv 1 1 0\n";
+        let floor = "f 1 4 3 2\n";
+        let wall = "f 1 2 6 5\n";
+
+        // The faces are counter-clockwise seen from above and from the
+        // front, so the normals point up and towards +Z.
+        let shared = load(&format!("{geometry}s 1\n{floor}{wall}")).unwrap();
+        let expected = Vec3::new(0.0, 1.0, 1.0).normalize();
+        let normals = vertex_normal(&shared.as_normal_vertices(), [0.0, 0.0, 0.0]);
+        assert!(!normals.is_empty());
+        assert!(normals.iter().all(|n| n.abs_diff_eq(expected, 1e-5)));
+
+        let split = load(&format!("{geometry}s 1\n{floor}s 2\n{wall}")).unwrap();
+        let normals = vertex_normal(&split.as_normal_vertices(), [0.0, 0.0, 0.0]);
+        assert!(normals.iter().any(|n| n.abs_diff_eq(Vec3::Y, 1e-5)));
+        assert!(normals.iter().any(|n| n.abs_diff_eq(Vec3::Z, 1e-5)));
+        assert!(normals
+            .iter()
+            .all(|n| n.abs_diff_eq(Vec3::Y, 1e-5) || n.abs_diff_eq(Vec3::Z, 1e-5)));
+
+        let off = load(&format!("{geometry}s off\n{floor}{wall}")).unwrap();
+        assert!(off.faces().iter().all(|f| f.smoothing == 0));
+    }
+
+    #[test]
+    fn concave_polygons_are_ear_clipped() {
+        // An L shape of three unit squares, concave at (1, 1)
+        let obj = "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nvn 0 0 1\n\
+                   f 1//1 2//1 3//1 4//1 5//1 6//1\n";
+        let loader = load(obj).unwrap();
+        let faces = loader.faces();
+        assert_eq!(faces.len(), 4);
+        let position = |i: usize| Vec3::from(loader.verts[i].vals);
+        let mut area = 0.0;
+        for face in faces {
+            let [a, b, c] = face.verts.map(position);
+            let normal = (b - a).cross(c - a);
+            // Every triangle keeps the orientation of the polygon
+            assert!(normal.z > 0.0);
+            area += normal.length() / 2.0;
+            // and lies inside it
+            let center = (a + b + c) / 3.0;
+            assert!(!(center.x > 1.0 && center.y > 1.0));
+        }
+        assert!((area - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn small_concave_polygons_are_ear_clipped() {
+        // The L shape of `concave_polygons_are_ear_clipped` a thousand times
+        // smaller, like a part modelled in meters with millimetre details.
+        // Starting at (2, 0), a fan would cross the notch at (1, 1).
+        let obj = "v 0.002 0 0\nv 0.002 0.001 0\nv 0.001 0.001 0\nv 0.001 0.002 0\n\
+                   v 0 0.002 0\nv 0 0 0\nvn 0 0 1\nf 1//1 2//1 3//1 4//1 5//1 6//1\n";
+        let loader = load(obj).unwrap();
+        let position = |i: usize| Vec3::from(loader.verts[i].vals) * 1000.0;
+        let mut area = 0.0;
+        for face in loader.faces() {
+            let [a, b, c] = face.verts.map(position);
+            let normal = (b - a).cross(c - a);
+            assert!(normal.z > 0.0);
+            area += normal.length() / 2.0;
+            let center = (a + b + c) / 3.0;
+            assert!(!(center.x > 1.0 && center.y > 1.0));
+        }
+        assert!((area - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn objects_groups_and_materials_split_sub_meshes() {
+        let resolver = |path: &str| -> io::Result<Box<dyn BufRead>> {
+            match path {
+                "colors.mtl" => Ok(Box::new(
+                    "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n".as_bytes(),
+                )),
+                _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        };
+        let obj = "mtllib colors.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+                   f 1 2 3\n\
+                   o body\nusemtl red\nf 1 2 3\nf 2 4 3\n\
+                   usemtl blue\nf 1 3 2\n\
+                   g lid\nf 2 4 3\n\
+                   o body\nusemtl red\nf 1 2 4\n";
+        let mut model = Model::from_obj_str(obj).resolver(resolver).build().unwrap();
+        let parts: Vec<_> = model
+            .sub_meshes()
+            .iter()
+            .map(|s| {
+                (
+                    s.object.as_str(),
+                    s.group.as_str(),
+                    s.material,
+                    s.indices.clone(),
+                )
+            })
+            .collect();
+        // Faces of a sub-mesh seen before join it again, so every sub-mesh
+        // is one range of the index buffer.
+        assert_eq!(
+            parts,
+            [
+                ("", "", None, 0..3),
+                ("body", "", Some(0), 3..12),
+                ("body", "", Some(1), 12..15),
+                ("body", "lid", Some(1), 15..18),
+            ]
+        );
+        assert_eq!(model.mesh().indices.len(), 18);
+        let colors: Vec<[f32; 3]> = model
+            .mesh()
+            .indices
+            .iter()
+            .skip(3)
+            .step_by(3)
+            .map(|i| model.mesh().vertices[i].color)
+            .collect();
+        assert_eq!(colors[..3], [[1.0, 0.0, 0.0]; 3]);
+        assert_eq!(colors[3..], [[0.0, 0.0, 1.0]; 2]);
+
+        // Objects and groups are both names of their sub-meshes.
+        let visible =
+            |model: &Model| -> Vec<bool> { model.sub_meshes().iter().map(|s| s.visible).collect() };
+        assert_eq!(visible(&model), [true; 4]);
+        model.set_visible("body", false);
+        assert_eq!(visible(&model), [true, false, false, false]);
+        model.set_visible("lid", true);
+        assert_eq!(visible(&model), [true, false, false, true]);
+        model.set_visible("missing", false);
+        assert_eq!(visible(&model), [true, false, false, true]);
+
+        let lift = Mat4::from_translation(Vec3::Y);
+        model.set_sub_mesh_transform("lid", lift);
+        let transforms: Vec<Mat4> = model.sub_meshes().iter().map(|s| s.transform).collect();
+        assert_eq!(
+            transforms,
+            [Mat4::IDENTITY, Mat4::IDENTITY, Mat4::IDENTITY, lift]
+        );
+        let (lid, _) = model.sub_mesh_matrices(&model.sub_meshes()[3]);
+        assert_eq!(lid, model.model_matrix() * lift);
+        // The shared mesh keeps the sub-meshes as loaded.
+        assert!(model.shared_mesh().sub_meshes().iter().all(|s| s.visible));
+    }
+
+    #[test]
+    fn texture_coordinates_follow_their_corners() {
+        // Every texture coordinate equals the position of its vertex, so
+        // misplaced ones are easy to spot.
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 0 1\nvt 1 1\nvn 0 0 1\n\
+                   f 2/2/1 4/4/1 3/3/1\n\
+                   f 1//1 2//1 3//1\n\
+                   f 1/1 2/2 4/4 3/3\n";
+        let loader = load(obj).unwrap();
+        let vertices = loader.as_textured_normal_vertices();
+        assert_eq!(vertices.len(), 3 * 4);
+        let positions: Vec<[f32; 3]> = loader
+            .as_normal_vertices()
+            .iter()
+            .map(|v| v.position)
+            .collect();
+        for (i, (vertex, position)) in vertices.iter().zip(&positions).enumerate() {
+            assert_eq!(vertex.position, *position);
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+            let expected = match i / 3 {
+                // `v//vn` corners have no texture coordinate.
+                1 => [0.0, 0.0],
+                _ => [position[0], position[1]],
+            };
+            assert_eq!(vertex.uv, expected, "corner {i}");
+        }
+
+        // The textured sub-meshes keep the same order.
+        let (mesh, _) = loader.as_textured_sub_meshes();
+        for vertex in mesh.to_triangle_list() {
+            if vertex.uv != [0.0, 0.0] {
+                assert_eq!(vertex.uv, [vertex.position[0], vertex.position[1]]);
+            }
+        }
+    }
+
+    #[test]
+    fn world_bounds_follow_the_transform_and_the_parent() {
+        let cube = Primitive::Cube { divisions: 1 }.mesh(COLOR, false);
+        let mut model = Model::from_mesh(Arc::new(cube));
+        let local = model.bounds();
+        let close = |a: Vec3, b: Vec3| a.abs_diff_eq(b, 1e-5);
+        assert!(close(model.world_bounds().center(), local.center()));
+        assert!(close(model.world_bounds().size(), local.size()));
+
+        // Every read fills the cache that the next change has to clear
+        model.set_translation(Vec3::new(5.0, 0.0, 0.0));
+        assert!(close(model.world_bounds().min, local.min + Vec3::X * 5.0));
+        assert!(close(
+            model.world_bounding_sphere().center,
+            model.bounding_sphere().center + Vec3::X * 5.0
+        ));
+
+        model.transform_mut().scale = Vec3::splat(2.0);
+        assert!(close(model.world_bounds().size(), local.size() * 2.0));
+        assert!(
+            (model.world_bounding_sphere().radius - model.bounding_sphere().radius * 2.0).abs()
+                < 1e-5
+        );
+
+        model.set_parent_matrix(Mat4::from_translation(Vec3::new(0.0, 10.0, 0.0)));
+        let center = local.center() * 2.0 + Vec3::new(5.0, 10.0, 0.0);
+        assert!(close(model.world_bounds().center(), center));
+
+        model.set_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        let bounds = model.world_bounds();
+        let expected = local.transformed(model.model_matrix());
+        assert!(close(bounds.min, expected.min) && close(bounds.max, expected.max));
+        assert!(bounds.size().x > local.size().x * 2.0);
     }
 }
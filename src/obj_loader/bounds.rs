@@ -0,0 +1,208 @@
+use glam::{Mat3, Mat4, Vec3};
+
+/// An axis-aligned bounding box, in whatever space its points are in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// The smallest box containing every point. Empty input gives a box at
+    /// the origin.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(p) => p,
+            None => {
+                return Aabb {
+                    min: Vec3::ZERO,
+                    max: Vec3::ZERO,
+                }
+            }
+        };
+        points.fold(
+            Aabb {
+                min: first,
+                max: first,
+            },
+            |b, p| Aabb {
+                min: b.min.min(p),
+                max: b.max.max(p),
+            },
+        )
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// The smallest box containing this box after an affine `transform`, in
+    /// the space the transform leads to, e.g. world space for a model matrix.
+    pub fn transformed(&self, transform: Mat4) -> Aabb {
+        let center = transform.transform_point3(self.center());
+        let half = self.size() * 0.5;
+        let axes = Mat3::from_mat4(transform);
+        let extent =
+            axes.x_axis.abs() * half.x + axes.y_axis.abs() * half.y + axes.z_axis.abs() * half.z;
+        Aabb {
+            min: center - extent,
+            max: center + extent,
+        }
+    }
+}
+
+/// A bounding sphere, in whatever space its points are in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    /// A sphere containing every point, close to the smallest one. Empty
+    /// input gives a sphere of radius 0 at the origin.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Sphere {
+        let points: Vec<Vec3> = points.into_iter().collect();
+        let first = match points.first() {
+            Some(p) => *p,
+            None => {
+                return Sphere {
+                    center: Vec3::ZERO,
+                    radius: 0.0,
+                }
+            }
+        };
+        // Ritter's algorithm: start with the two points farthest apart that
+        // two passes find, then grow the sphere towards every point outside.
+        let farthest = |from: Vec3| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| {
+                    a.distance_squared(from)
+                        .total_cmp(&b.distance_squared(from))
+                })
+                .unwrap()
+        };
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut sphere = Sphere {
+            center: (a + b) * 0.5,
+            radius: a.distance(b) * 0.5,
+        };
+        for p in &points {
+            let distance = p.distance(sphere.center);
+            if distance > sphere.radius {
+                let radius = (sphere.radius + distance) * 0.5;
+                sphere.center += (*p - sphere.center) * ((radius - sphere.radius) / distance);
+                sphere.radius = radius;
+            }
+        }
+        sphere
+    }
+
+    /// The sphere containing this sphere after an affine `transform`. Non
+    /// uniform scales grow the radius by the largest factor.
+    pub fn transformed(&self, transform: Mat4) -> Sphere {
+        let axes = Mat3::from_mat4(transform);
+        let scale = axes
+            .x_axis
+            .length()
+            .max(axes.y_axis.length())
+            .max(axes.z_axis.length());
+        Sphere {
+            center: transform.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use glam::Quat;
+
+    use super::*;
+
+    #[test]
+    fn rotated_boxes_grow_to_contain_their_corners() {
+        let unit = Aabb {
+            min: Vec3::new(-1.0, -1.0, -1.0),
+            max: Vec3::new(1.0, 1.0, 1.0),
+        };
+        let turned = Mat4::from_rotation_translation(
+            Quat::from_rotation_z(FRAC_PI_4),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        let bounds = unit.transformed(turned);
+        let half_diagonal = 2.0f32.sqrt();
+        let expected = Aabb {
+            min: Vec3::new(10.0 - half_diagonal, -half_diagonal, -1.0),
+            max: Vec3::new(10.0 + half_diagonal, half_diagonal, 1.0),
+        };
+        assert!(bounds.min.abs_diff_eq(expected.min, 1e-5), "{bounds:?}");
+        assert!(bounds.max.abs_diff_eq(expected.max, 1e-5), "{bounds:?}");
+
+        // A quarter turn and a scale give a box again, with its axes swapped.
+        let flat = Aabb {
+            min: Vec3::ZERO,
+            max: Vec3::new(4.0, 1.0, 2.0),
+        };
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_z(FRAC_PI_4 * 2.0),
+            Vec3::ZERO,
+        );
+        let bounds = flat.transformed(transform);
+        assert!(bounds.min.abs_diff_eq(Vec3::new(-2.0, 0.0, 0.0), 1e-5));
+        assert!(bounds.max.abs_diff_eq(Vec3::new(0.0, 8.0, 4.0), 1e-5));
+    }
+
+    #[test]
+    fn spheres_contain_every_point() {
+        // A deterministic scatter of points, stretched along X
+        let mut state = 12345u32;
+        let mut next = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0
+        };
+        let points: Vec<Vec3> = (0..500)
+            .map(|_| Vec3::new(next() * 5.0 + 3.0, next(), next() * 2.0))
+            .collect();
+        let sphere = Sphere::from_points(points.iter().copied());
+        for p in &points {
+            assert!(p.distance(sphere.center) <= sphere.radius * (1.0 + 1e-5));
+        }
+        // Close to the smallest sphere, not the box's circumscribed one
+        let bounds = Aabb::from_points(points.iter().copied());
+        assert!(sphere.radius >= bounds.size().x / 2.0);
+        assert!(sphere.radius < bounds.size().length() / 2.0 * 1.2);
+
+        let empty = Sphere::from_points(std::iter::empty());
+        assert_eq!((empty.center, empty.radius), (Vec3::ZERO, 0.0));
+    }
+
+    #[test]
+    fn transformed_spheres_grow_by_the_largest_scale() {
+        let sphere = Sphere {
+            center: Vec3::X,
+            radius: 2.0,
+        };
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::new(1.0, 3.0, 0.5),
+            Quat::from_rotation_y(1.0),
+            Vec3::new(0.0, 5.0, 0.0),
+        );
+        let moved = sphere.transformed(transform);
+        assert!((moved.radius - 6.0).abs() < 1e-5);
+        assert!(moved
+            .center
+            .abs_diff_eq(transform.transform_point3(Vec3::X), 1e-6));
+    }
+}
@@ -0,0 +1,486 @@
+use std::fs;
+
+use glam::{Mat4, Vec3};
+
+use super::mesh::MeshId;
+use super::{Aabb, IndexedMesh, Indices, Material, Mesh, NormalVertex, ObjError, Sphere, SubMesh};
+
+// Layout of a cache file, all numbers little-endian:
+//
+//   magic     8 bytes
+//   version   u32
+//   options   u64, fingerprint of the builder options the mesh was made with
+//   checksum  u64, FNV-1a of the payload
+//   length    u64, size of the payload in bytes
+//   payload   bounds, sphere, vertices, uvs, tangents, indices, sub-meshes, materials
+//
+// Bump VERSION whenever the payload changes, so that stale caches are
+// rebuilt instead of misread.
+const MAGIC: [u8; 8] = *b"LVKMESH\0";
+const VERSION: u32 = 3;
+const HEADER_LEN: usize = 8 + 4 + 8 + 8 + 8;
+
+/// 64-bit FNV-1a, used both as the checksum and to fingerprint options.
+pub(super) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub(super) fn write(mesh: &Mesh, options: u64, path: &str) -> Result<(), ObjError> {
+    fs::write(path, encode(mesh, options)).map_err(|source| ObjError::Io {
+        path: path.to_string(),
+        source,
+    })
+}
+
+fn encode(mesh: &Mesh, options: u64) -> Vec<u8> {
+    let mut payload = Writer::default();
+    payload.vec3(mesh.bounds.min);
+    payload.vec3(mesh.bounds.max);
+    payload.vec3(mesh.sphere.center);
+    payload.f32s(&[mesh.sphere.radius]);
+
+    payload.u32(mesh.geometry.vertices.len() as u32);
+    for vertex in &mesh.geometry.vertices {
+        payload.f32s(&vertex.position);
+        payload.f32s(&vertex.normal);
+        payload.f32s(&vertex.color);
+    }
+    for uv in &mesh.uvs {
+        payload.f32s(uv);
+    }
+    payload.u32(mesh.tangents.len() as u32);
+    for tangent in &mesh.tangents {
+        payload.f32s(tangent);
+    }
+
+    match &mesh.geometry.indices {
+        Indices::U16(indices) => {
+            payload.u8(16);
+            payload.u32(indices.len() as u32);
+            indices.iter().for_each(|i| payload.bytes(&i.to_le_bytes()));
+        }
+        Indices::U32(indices) => {
+            payload.u8(32);
+            payload.u32(indices.len() as u32);
+            indices.iter().for_each(|i| payload.u32(*i));
+        }
+    }
+
+    payload.u32(mesh.sub_meshes.len() as u32);
+    for sub_mesh in &mesh.sub_meshes {
+        payload.string(&sub_mesh.object);
+        payload.string(&sub_mesh.group);
+        payload.u32(sub_mesh.material.map_or(u32::MAX, |m| m as u32));
+        payload.u32(sub_mesh.indices.start);
+        payload.u32(sub_mesh.indices.end);
+    }
+
+    payload.u32(mesh.materials.len() as u32);
+    for material in &mesh.materials {
+        payload.string(&material.name);
+        payload.string(&material.library);
+        payload.f32s(&material.ambient);
+        payload.f32s(&material.diffuse);
+        payload.f32s(&material.specular);
+        payload.f32s(&material.emissive);
+        payload.f32s(&[material.shininess, material.dissolve]);
+        payload.u32(material.illumination_model);
+        for map in material.maps() {
+            match map {
+                Some(map) => {
+                    payload.u8(1);
+                    payload.string(map);
+                }
+                None => payload.u8(0),
+            }
+        }
+    }
+
+    let payload = payload.0;
+    let mut file = Writer(Vec::with_capacity(HEADER_LEN + payload.len()));
+    file.bytes(&MAGIC);
+    file.u32(VERSION);
+    file.u64(options);
+    file.u64(fnv1a(&payload));
+    file.u64(payload.len() as u64);
+    file.bytes(&payload);
+    file.0
+}
+
+/// Reads a cache file, returning the options fingerprint it was written with
+/// and the mesh.
+pub(super) fn read(path: &str) -> Result<(u64, Mesh), ObjError> {
+    let bytes = fs::read(path).map_err(|source| ObjError::Io {
+        path: path.to_string(),
+        source,
+    })?;
+    parse(&bytes).map_err(|e| e.at(path, 0))
+}
+
+fn parse(bytes: &[u8]) -> Result<(u64, Mesh), ObjError> {
+    let mut header = Reader(bytes);
+    if header.take(MAGIC.len())? != MAGIC {
+        return Err(ObjError::invalid_cache("not a mesh cache"));
+    }
+    if header.u32()? != VERSION {
+        return Err(ObjError::invalid_cache("unsupported version"));
+    }
+    let options = header.u64()?;
+    let checksum = header.u64()?;
+    let length = header.u64()?;
+    if header.0.len() as u64 != length {
+        return Err(ObjError::invalid_cache("unexpected file size"));
+    }
+    if fnv1a(header.0) != checksum {
+        return Err(ObjError::invalid_cache("checksum mismatch"));
+    }
+
+    let mut payload = Reader(header.0);
+    let bounds = Aabb {
+        min: payload.vec3()?,
+        max: payload.vec3()?,
+    };
+    let sphere = Sphere {
+        center: payload.vec3()?,
+        radius: payload.f32s::<1>()?[0],
+    };
+
+    let vertex_count = payload.count(9 * 4)?;
+    let mut vertices = Vec::with_capacity(vertex_count);
+    for _ in 0..vertex_count {
+        vertices.push(NormalVertex {
+            position: payload.f32s()?,
+            normal: payload.f32s()?,
+            color: payload.f32s()?,
+        });
+    }
+    let mut uvs = Vec::with_capacity(vertex_count);
+    for _ in 0..vertex_count {
+        uvs.push(payload.f32s()?);
+    }
+    let tangent_count = payload.count(4 * 4)?;
+    if tangent_count != 0 && tangent_count != vertex_count {
+        return Err(ObjError::invalid_cache("tangent count mismatch"));
+    }
+    let mut tangents = Vec::with_capacity(tangent_count);
+    for _ in 0..tangent_count {
+        tangents.push(payload.f32s()?);
+    }
+
+    let indices = match payload.u8()? {
+        16 => {
+            let count = payload.count(2)?;
+            let mut indices = Vec::with_capacity(count);
+            for _ in 0..count {
+                let bytes = payload.take(2)?;
+                indices.push(u16::from_le_bytes([bytes[0], bytes[1]]));
+            }
+            Indices::U16(indices)
+        }
+        32 => {
+            let count = payload.count(4)?;
+            let mut indices = Vec::with_capacity(count);
+            for _ in 0..count {
+                indices.push(payload.u32()?);
+            }
+            Indices::U32(indices)
+        }
+        _ => return Err(ObjError::invalid_cache("unknown index type")),
+    };
+    if indices.iter().any(|i| i >= vertex_count) {
+        return Err(ObjError::invalid_cache("index out of range"));
+    }
+
+    let sub_mesh_count = payload.count(4 * 5)?;
+    let mut sub_meshes = Vec::with_capacity(sub_mesh_count);
+    for _ in 0..sub_mesh_count {
+        let object = payload.string()?;
+        let group = payload.string()?;
+        let material = match payload.u32()? {
+            u32::MAX => None,
+            m => Some(m as usize),
+        };
+        let range = payload.u32()?..payload.u32()?;
+        if range.start > range.end || range.end as usize > indices.len() {
+            return Err(ObjError::invalid_cache("sub-mesh out of range"));
+        }
+        sub_meshes.push(SubMesh {
+            object,
+            group,
+            material,
+            indices: range,
+            transform: Mat4::IDENTITY,
+            visible: true,
+        });
+    }
+
+    let material_count = payload.count(4)?;
+    let mut materials = Vec::with_capacity(material_count);
+    for _ in 0..material_count {
+        let mut material = Material::new(&payload.string()?);
+        material.library = payload.string()?;
+        material.ambient = payload.f32s()?;
+        material.diffuse = payload.f32s()?;
+        material.specular = payload.f32s()?;
+        material.emissive = payload.f32s()?;
+        [material.shininess, material.dissolve] = payload.f32s()?;
+        material.illumination_model = payload.u32()?;
+        for map in material.maps_mut() {
+            *map = match payload.u8()? {
+                0 => None,
+                _ => Some(payload.string()?),
+            };
+        }
+        materials.push(material);
+    }
+    if sub_meshes
+        .iter()
+        .any(|s| s.material.is_some_and(|m| m >= materials.len()))
+    {
+        return Err(ObjError::invalid_cache("material out of range"));
+    }
+    if !payload.0.is_empty() {
+        return Err(ObjError::invalid_cache("trailing data"));
+    }
+
+    let mesh = Mesh {
+        geometry: IndexedMesh { vertices, indices },
+        uvs,
+        tangents,
+        sub_meshes,
+        materials,
+        bounds,
+        sphere,
+        generation: 0,
+        id: MeshId::new(),
+    };
+    Ok((options, mesh))
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, x: u8) {
+        self.0.push(x);
+    }
+
+    fn u32(&mut self, x: u32) {
+        self.bytes(&x.to_le_bytes());
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.bytes(&x.to_le_bytes());
+    }
+
+    fn f32s(&mut self, xs: &[f32]) {
+        xs.iter().for_each(|x| self.bytes(&x.to_le_bytes()));
+    }
+
+    fn vec3(&mut self, v: Vec3) {
+        self.f32s(&v.to_array());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.bytes(s.as_bytes());
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ObjError> {
+        if self.0.len() < n {
+            return Err(ObjError::invalid_cache("truncated"));
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, ObjError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ObjError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ObjError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // Reads an element count and checks that the elements, at least
+    // `min_size` bytes each, can still fit, so that a corrupt count can't
+    // trigger a huge allocation.
+    fn count(&mut self, min_size: usize) -> Result<usize, ObjError> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(min_size) > self.0.len() {
+            return Err(ObjError::invalid_cache("truncated"));
+        }
+        Ok(count)
+    }
+
+    fn f32s<const N: usize>(&mut self) -> Result<[f32; N], ObjError> {
+        let mut xs = [0.0; N];
+        for x in &mut xs {
+            *x = f32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        }
+        Ok(xs)
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, ObjError> {
+        Ok(Vec3::from_array(self.f32s()?))
+    }
+
+    fn string(&mut self) -> Result<String, ObjError> {
+        let len = self.count(1)?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| ObjError::invalid_cache("invalid string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead};
+
+    use super::*;
+    use crate::obj_loader::{Model, Winding};
+
+    const OBJ: &str = "mtllib colors.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                       vt 0 0\nvt 1 0\nvt 1 1\no square\nusemtl red\nf 1/1 2/2 3/3\n\
+                       g corner\nusemtl None\nf 1 3 4\n";
+
+    fn mesh() -> Mesh {
+        let resolver = |path: &str| -> io::Result<Box<dyn BufRead>> {
+            match path {
+                "colors.mtl" => Ok(Box::new(
+                    "newmtl red\nKd 1 0 0\nmap_Kd red.png\n".as_bytes(),
+                )),
+                _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        };
+        Model::from_obj_str(OBJ)
+            .resolver(resolver)
+            .build_mesh()
+            .unwrap()
+    }
+
+    fn reason(bytes: &[u8]) -> &'static str {
+        match parse(bytes) {
+            Err(ObjError::InvalidCache { reason, .. }) => reason,
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => panic!("corrupt cache accepted"),
+        }
+    }
+
+    // Rewrites the length and checksum after the payload was edited, so that
+    // only the payload itself is wrong.
+    fn reseal(bytes: &mut [u8]) {
+        let payload = bytes[HEADER_LEN..].to_vec();
+        bytes[20..28].copy_from_slice(&fnv1a(&payload).to_le_bytes());
+        bytes[28..36].copy_from_slice(&(payload.len() as u64).to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mesh = mesh();
+        let (options, read) = parse(&encode(&mesh, 42)).unwrap();
+        assert_eq!(options, 42);
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&read.geometry.vertices),
+            bytemuck::cast_slice::<_, u8>(&mesh.geometry.vertices)
+        );
+        assert_eq!(read.geometry.indices, mesh.geometry.indices);
+        assert_eq!(read.uvs, mesh.uvs);
+        assert_eq!(read.tangents, mesh.tangents);
+        assert_eq!(read.materials, mesh.materials);
+        assert_eq!(read.materials[0].diffuse_map.as_deref(), Some("red.png"));
+        assert_eq!(read.sub_meshes.len(), 2);
+        for (read, sub_mesh) in read.sub_meshes.iter().zip(&mesh.sub_meshes) {
+            assert_eq!(read.object, sub_mesh.object);
+            assert_eq!(read.group, sub_mesh.group);
+            assert_eq!(read.material, sub_mesh.material);
+            assert_eq!(read.indices, sub_mesh.indices);
+        }
+        assert_eq!(read.bounds, mesh.bounds);
+        assert_eq!(read.sphere, mesh.sphere);
+    }
+
+    #[test]
+    fn corrupt_caches_are_rejected() {
+        let bytes = encode(&mesh(), 0);
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(reason(&bad_magic), "not a mesh cache");
+
+        let mut old_version = bytes.clone();
+        old_version[8..12].copy_from_slice(&(VERSION - 1).to_le_bytes());
+        assert_eq!(reason(&old_version), "unsupported version");
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert_eq!(reason(&flipped), "checksum mismatch");
+
+        assert_eq!(reason(&bytes[..bytes.len() - 1]), "unexpected file size");
+        assert_eq!(reason(&bytes[..HEADER_LEN - 1]), "truncated");
+        let mut truncated = bytes[..bytes.len() - 1].to_vec();
+        reseal(&mut truncated);
+        assert_eq!(reason(&truncated), "truncated");
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        reseal(&mut trailing);
+        assert_eq!(reason(&trailing), "trailing data");
+    }
+
+    #[test]
+    fn options_fingerprint_tells_builders_apart() {
+        let builder = || Model::from_obj_str(OBJ);
+        let options = builder().options_fingerprint();
+        assert_eq!(options, builder().options_fingerprint());
+        let (cached, _) = parse(&encode(&mesh(), options)).unwrap();
+
+        // The builder ignores caches whose fingerprint differs from its own.
+        for other in [
+            builder().color([1.0, 0.0, 0.0]),
+            builder().winding_order(Winding::Clockwise),
+            builder().crease_angle(0.5),
+        ] {
+            assert_ne!(cached, other.options_fingerprint());
+        }
+    }
+
+    #[test]
+    fn builders_write_caches_only_when_asked() {
+        let dir = std::env::temp_dir().join(format!("learn-vulkano-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let obj = dir.join("triangle.obj");
+        fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let obj = obj.to_str().unwrap().to_string();
+        let cache_file = Model::cache_path(&obj);
+
+        let loaded = Model::builder(&obj).build().unwrap();
+        assert!(!std::path::Path::new(&cache_file).exists());
+
+        Model::builder(&obj).write_cache(true).build().unwrap();
+        let (_, cached) = read(&cache_file).unwrap();
+        assert_eq!(cached.geometry.indices, loaded.mesh().indices);
+
+        // A cache that can't be written doesn't fail the load.
+        fs::remove_file(&cache_file).unwrap();
+        fs::create_dir(&cache_file).unwrap();
+        let rebuilt = Model::builder(&obj).write_cache(true).build().unwrap();
+        assert_eq!(rebuilt.mesh().indices, loaded.mesh().indices);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use glam::{Mat4, Vec3};
+
+use super::{Mesh, ObjError, Winding};
+
+// The keywords of the texture maps, in the order of `Material::maps`.
+const MAP_KEYWORDS: [&str; 7] = [
+    "map_Ka", "map_Kd", "map_Ks", "map_Ke", "map_Ns", "map_d", "map_Bump",
+];
+
+/// Writes a mesh back out as an obj file and its materials as an mtl library.
+///
+/// Every vertex is written once as a `v`, `vt` and `vn` line, and faces refer
+/// to them by index, so shared vertices stay shared. Sub-meshes become `o`,
+/// `g` and `usemtl` statements. Faces without a material follow a bare
+/// `usemtl` and get the color of the [`super::ModelBuilder`] that loads the
+/// file again, since obj files have no vertex colors. Sub-mesh transforms
+/// and visibility are not exported.
+pub struct ObjWriter<'a> {
+    mesh: &'a Mesh,
+    // The model and normal matrices applied by `bake_transform`
+    matrices: (Mat4, Mat4),
+    bake_transform: bool,
+    // `None` picks the order from the winding of the mesh
+    invert: Option<bool>,
+}
+
+impl<'a> ObjWriter<'a> {
+    pub fn new(mesh: &'a Mesh) -> ObjWriter<'a> {
+        ObjWriter::with_matrices(mesh, (Mat4::IDENTITY, Mat4::IDENTITY))
+    }
+
+    pub(super) fn with_matrices(mesh: &'a Mesh, matrices: (Mat4, Mat4)) -> ObjWriter<'a> {
+        ObjWriter {
+            mesh,
+            matrices,
+            bake_transform: false,
+            invert: None,
+        }
+    }
+
+    /// Applies the model matrix to the positions and the normal matrix to
+    /// the normals, when writing a [`super::Model`]. Off by default, which
+    /// writes the geometry in model space.
+    pub fn bake_transform(mut self, bake: bool) -> ObjWriter<'a> {
+        self.bake_transform = bake;
+        self
+    }
+
+    /// Whether to reverse the winding order of the faces, so that loading
+    /// the file with the same setting on [`super::ModelBuilder`] gives back
+    /// the same triangles.
+    ///
+    /// By default faces are written counter-clockwise, like most obj files,
+    /// so they are reversed unless [`Mesh::winding_order`] finds the mesh
+    /// counter-clockwise already. The builder then detects the order again
+    /// when loading the file.
+    pub fn invert_winding_order(mut self, invert: bool) -> ObjWriter<'a> {
+        self.invert = Some(invert);
+        self
+    }
+
+    /// Writes `path` and, if the mesh has materials, an mtl library next to
+    /// it with the extension `mtl`.
+    pub fn save(&self, path: &str) -> Result<(), ObjError> {
+        let io_error = |path: &Path| {
+            let path = path.to_string_lossy().into_owned();
+            move |source| ObjError::Io { path, source }
+        };
+        let obj_path = Path::new(path);
+        let mtl_path = obj_path.with_extension("mtl");
+        let mut library = None;
+        if !self.mesh.materials.is_empty() {
+            let file = File::create(&mtl_path).map_err(io_error(&mtl_path))?;
+            let mut mtl = BufWriter::new(file);
+            self.write_mtl(&mut mtl)
+                .and_then(|_| mtl.flush())
+                .map_err(io_error(&mtl_path))?;
+            library = mtl_path.file_name().map(|name| name.to_string_lossy());
+        }
+        let mut obj = BufWriter::new(File::create(obj_path).map_err(io_error(obj_path))?);
+        self.write_obj(&mut obj, library.as_deref())
+            .and_then(|_| obj.flush())
+            .map_err(io_error(obj_path))
+    }
+
+    /// Writes the obj file, referencing the mtl library `library` if given.
+    pub fn write_obj(&self, out: &mut impl Write, library: Option<&str>) -> io::Result<()> {
+        // Values are only touched when baking, so that exporting loaded data
+        // writes it back unchanged.
+        let (model, normal) = self.matrices;
+        let position = |p: [f32; 3]| {
+            if self.bake_transform {
+                model.transform_point3(Vec3::from(p))
+            } else {
+                Vec3::from(p)
+            }
+        };
+        let normal = |n: [f32; 3]| {
+            if self.bake_transform {
+                normal.transform_vector3(Vec3::from(n)).normalize_or_zero()
+            } else {
+                Vec3::from(n)
+            }
+        };
+        let geometry = &self.mesh.geometry;
+        let invert = self
+            .invert
+            .unwrap_or_else(|| self.mesh.winding_order() != Some(Winding::CounterClockwise));
+        // Mirroring the positions turns the faces inside out, which one more
+        // reversal undoes.
+        let invert = invert != (self.bake_transform && model.determinant() < 0.0);
+
+        if let Some(library) = library {
+            writeln!(out, "mtllib {}", library)?;
+        }
+        for vertex in &geometry.vertices {
+            let p = position(vertex.position);
+            writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+        }
+        for uv in &self.mesh.uvs {
+            writeln!(out, "vt {} {}", uv[0], uv[1])?;
+        }
+        for vertex in &geometry.vertices {
+            let n = normal(vertex.normal);
+            writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+
+        let (mut object, mut group, mut material) = ("", "", None);
+        for sub_mesh in &self.mesh.sub_meshes {
+            // `o` starts an object without a group, which is also how to get
+            // back to the faces of an object outside of any group.
+            if sub_mesh.object != object || (sub_mesh.group.is_empty() && !group.is_empty()) {
+                object = &sub_mesh.object;
+                group = "";
+                writeln!(out, "o {}", object)?;
+            }
+            if sub_mesh.group != group {
+                group = &sub_mesh.group;
+                writeln!(out, "g {}", group)?;
+            }
+            // The material carries over to later sub-meshes, and an empty
+            // name matches no material, which falls back to the builder
+            // color.
+            if sub_mesh.material != material {
+                material = sub_mesh.material;
+                match material {
+                    Some(m) => writeln!(out, "usemtl {}", self.mesh.materials[m].name)?,
+                    None => writeln!(out, "usemtl")?,
+                }
+            }
+            let indices = sub_mesh.indices.start as usize..sub_mesh.indices.end as usize;
+            for first in indices.step_by(3) {
+                let corner = |i: usize| geometry.indices.get(first + i).unwrap() + 1;
+                let (a, b, c) = if invert {
+                    (corner(0), corner(2), corner(1))
+                } else {
+                    (corner(0), corner(1), corner(2))
+                };
+                writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes every material of the mesh as an mtl library.
+    pub fn write_mtl(&self, out: &mut impl Write) -> io::Result<()> {
+        for material in &self.mesh.materials {
+            let color = |out: &mut dyn Write, keyword: &str, c: [f32; 3]| {
+                writeln!(out, "{} {} {} {}", keyword, c[0], c[1], c[2])
+            };
+            writeln!(out, "newmtl {}", material.name)?;
+            color(out, "Ka", material.ambient)?;
+            color(out, "Kd", material.diffuse)?;
+            color(out, "Ks", material.specular)?;
+            color(out, "Ke", material.emissive)?;
+            writeln!(out, "Ns {}", material.shininess)?;
+            writeln!(out, "d {}", material.dissolve)?;
+            writeln!(out, "illum {}", material.illumination_model)?;
+            for (keyword, map) in MAP_KEYWORDS.iter().zip(material.maps()) {
+                if let Some(map) = map {
+                    writeln!(out, "{} {}", keyword, map)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead};
+
+    use glam::{Mat4, Quat, Vec3};
+
+    use super::ObjWriter;
+    use crate::obj_loader::{IndexedMesh, Loader, Model, SubMesh};
+    use crate::transform::Transform;
+
+    const COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+    // A unit cube with a material on two objects, a group, and faces
+    // without a material in and outside of the group.
+    const OBJ: &str = "mtllib colors.mtl\n\
+                       v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                       v 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                       o bottom\nusemtl red\nf 1 4 3 2\n\
+                       g sides\nf 1 2 6 5\nusemtl None\nf 2 3 7 6\n\
+                       o top\nf 3 4 8 7\nusemtl red\nf 5 6 7 8\n\
+                       g back\nf 1 5 8 4\n";
+
+    // Writes the model and reads the file back, as the faces the renderer
+    // draws.
+    fn round_trip(writer: ObjWriter) -> (String, IndexedMesh, Vec<SubMesh>) {
+        let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+        writer.write_obj(&mut obj, Some("out.mtl")).unwrap();
+        writer.write_mtl(&mut mtl).unwrap();
+        let resolver = move |path: &str| -> io::Result<Box<dyn BufRead>> {
+            assert_eq!(path, "out.mtl");
+            Ok(Box::new(io::Cursor::new(mtl.clone())))
+        };
+        // The file is counter-clockwise, which is turned around for the
+        // renderer.
+        let loader = Loader::from_reader(&obj[..], "out.obj", &resolver, COLOR, true).unwrap();
+        let (mesh, sub_meshes) = loader.as_sub_meshes();
+        (String::from_utf8(obj).unwrap(), mesh, sub_meshes)
+    }
+
+    // The position and normal of every corner, in drawing order.
+    fn corners(mesh: &IndexedMesh, transform: Mat4) -> Vec<(Vec3, Vec3)> {
+        let normal_matrix = transform.inverse().transpose();
+        (mesh.indices.iter())
+            .map(|i| mesh.vertices[i])
+            .map(|v| {
+                let position = transform.transform_point3(Vec3::from(v.position));
+                let normal = normal_matrix.transform_vector3(Vec3::from(v.normal));
+                (position, normal.normalize())
+            })
+            .collect()
+    }
+
+    // Whether the faces, in drawing order, turn the same way around their
+    // vertex normals.
+    fn windings(corners: &[(Vec3, Vec3)]) -> Vec<bool> {
+        corners
+            .chunks(3)
+            .map(|c| {
+                let face = (c[1].0 - c[0].0).cross(c[2].0 - c[0].0);
+                face.dot(c[0].1 + c[1].1 + c[2].1) > 0.0
+            })
+            .collect()
+    }
+
+    fn assert_close(a: &[(Vec3, Vec3)], b: &[(Vec3, Vec3)]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert!(a.0.abs_diff_eq(b.0, 1e-5), "{a:?} != {b:?}");
+            assert!(a.1.abs_diff_eq(b.1, 1e-5), "{a:?} != {b:?}");
+        }
+    }
+
+    fn model() -> Model {
+        let resolver = |path: &str| -> io::Result<Box<dyn BufRead>> {
+            assert_eq!(path, "colors.mtl");
+            Ok(Box::new("newmtl red\nKd 1 0 0\n".as_bytes()))
+        };
+        Model::from_obj_str(OBJ).resolver(resolver).build().unwrap()
+    }
+
+    #[test]
+    fn written_files_load_back_the_same() {
+        let model = model();
+        let (obj, mesh, sub_meshes) = round_trip(model.obj_writer());
+        assert!(!obj.contains("usemtl None"));
+        assert!(!obj.lines().any(|line| line.trim() == "g"));
+
+        let original = corners(model.mesh(), Mat4::IDENTITY);
+        let read = corners(&mesh, Mat4::IDENTITY);
+        assert_close(&read, &original);
+        assert_eq!(windings(&read), windings(&original));
+
+        assert_eq!(sub_meshes.len(), model.sub_meshes().len());
+        for (read, sub_mesh) in sub_meshes.iter().zip(model.sub_meshes()) {
+            assert_eq!(read.object, sub_mesh.object);
+            assert_eq!(read.group, sub_mesh.group);
+            assert_eq!(read.material, sub_mesh.material);
+            assert_eq!(read.indices, sub_mesh.indices);
+        }
+    }
+
+    #[test]
+    fn baked_mirror_keeps_faces_outward() {
+        let mut model = model();
+        *model.transform_mut() = Transform {
+            translation: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(0.5),
+            scale: Vec3::new(-2.0, 1.0, 1.0),
+        };
+        let transform = model.model_matrix();
+        let (_, mesh, _) = round_trip(model.obj_writer().bake_transform(true));
+
+        // The mirror turns the faces around in world space, which the
+        // exporter undoes by swapping two corners of every face, so they
+        // stay wound as in model space.
+        let read = corners(&mesh, Mat4::IDENTITY);
+        let mirrored: Vec<_> = corners(model.mesh(), transform)
+            .chunks(3)
+            .flat_map(|c| [c[0], c[2], c[1]])
+            .collect();
+        assert_close(&read, &mirrored);
+        assert_eq!(
+            windings(&read),
+            windings(&corners(model.mesh(), Mat4::IDENTITY))
+        );
+    }
+}
@@ -0,0 +1,750 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use glam::{Mat4, Vec3};
+use gltf::{buffer, mesh::Mode};
+
+use super::{
+    mesh, CullMode, IndexedMesh, Indices, Material, Mesh, Model, NormalVertex, ObjError, SubMesh,
+};
+
+/// Where the image of a [`PbrMaterial`] texture is found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureSource {
+    /// A path relative to the glTF file, or a `data:` URI, as written in the
+    /// file.
+    Uri(String),
+    /// Encoded image data stored in one of the file's buffers.
+    Embedded { mime_type: String, data: Vec<u8> },
+}
+
+/// A metallic-roughness material of a glTF file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbrMaterial {
+    pub name: String,
+    /// Linear RGBA, multiplied with `base_color_texture`
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub base_color_texture: Option<TextureSource>,
+    /// Roughness in the green channel, metalness in the blue channel
+    pub metallic_roughness_texture: Option<TextureSource>,
+    pub normal_texture: Option<TextureSource>,
+    pub occlusion_texture: Option<TextureSource>,
+    pub emissive_texture: Option<TextureSource>,
+    /// Set for materials that discard fragments with a lower alpha.
+    pub alpha_cutoff: Option<f32>,
+    /// Whether the material is alpha blended.
+    pub blend: bool,
+    /// Whether both sides of the faces are drawn. [`GltfScene::models`]
+    /// draws meshes with such a material with [`CullMode::None`], while
+    /// files loaded through [`super::ModelBuilder`] use its cull mode.
+    pub double_sided: bool,
+}
+
+/// A node of the glTF node hierarchy.
+#[derive(Clone, Debug)]
+pub struct GltfNode {
+    pub name: String,
+    /// Relative to the parent node.
+    pub transform: Mat4,
+    /// Index into [`GltfScene::meshes`].
+    pub mesh: Option<usize>,
+    /// Indices into [`GltfScene::nodes`].
+    pub children: Vec<usize>,
+}
+
+/// The meshes, materials and nodes of a glTF 2.0 file.
+///
+/// Every glTF mesh becomes one [`Mesh`] with a sub-mesh per primitive, named
+/// after the mesh. Vertex colors are the base color of the material,
+/// multiplied with the `COLOR_0` attribute if present. Primitives without
+/// normals get flat normals. Faces are converted to the winding order of
+/// [`super::ModelBuilder`]'s default, so glTF and obj models can be drawn by
+/// the same pipelines.
+pub struct GltfScene {
+    meshes: Vec<Arc<Mesh>>,
+    materials: Vec<PbrMaterial>,
+    nodes: Vec<GltfNode>,
+    roots: Vec<usize>,
+}
+
+impl GltfScene {
+    /// Reads a `.gltf` or `.glb` file. External buffers are read relative to
+    /// the directory of the file.
+    pub fn load(file_name: &str) -> Result<GltfScene, ObjError> {
+        let bytes = std::fs::read(file_name).map_err(|source| ObjError::Io {
+            path: file_name.to_string(),
+            source,
+        })?;
+        let base = Path::new(file_name).parent().unwrap_or(Path::new(""));
+        GltfScene::import(&bytes, Some(base)).map_err(|e| e.at(file_name, 0))
+    }
+
+    /// Reads glTF or glb data held in memory. External buffers are read
+    /// relative to `base`, and are an error without it. Errors are reported
+    /// against the file name `<slice>`.
+    pub fn from_slice(bytes: &[u8], base: Option<&Path>) -> Result<GltfScene, ObjError> {
+        GltfScene::import(bytes, base).map_err(|e| e.at("<slice>", 0))
+    }
+
+    fn import(bytes: &[u8], base: Option<&Path>) -> Result<GltfScene, ObjError> {
+        let gltf_error = |source| ObjError::Gltf {
+            path: String::new(),
+            source,
+        };
+        let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(bytes).map_err(gltf_error)?;
+        let buffers = gltf::import_buffers(&document, base, blob).map_err(gltf_error)?;
+
+        let materials: Vec<PbrMaterial> = document
+            .materials()
+            .map(|m| pbr_material(&m, &buffers))
+            .collect::<Result<_, _>>()?;
+        // The materials in the form used by obj models, shared by all meshes
+        // so that their indices match `materials`
+        let mtl_materials: Vec<Material> = materials.iter().map(to_mtl_material).collect();
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| import_mesh(&mesh, &buffers, &materials, &mtl_materials).map(Arc::new))
+            .collect::<Result<_, _>>()?;
+
+        let nodes: Vec<GltfNode> = document
+            .nodes()
+            .map(|node| GltfNode {
+                name: node.name().unwrap_or("").to_string(),
+                transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+                mesh: node.mesh().map(|m| m.index()),
+                children: node.children().map(|c| c.index()).collect(),
+            })
+            .collect();
+
+        // Files without scenes still have their nodes drawn, starting at the
+        // ones that aren't the child of another.
+        let roots: Vec<usize> = match document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            Some(scene) => scene.nodes().map(|n| n.index()).collect(),
+            None => (0..nodes.len())
+                .filter(|i| !nodes.iter().any(|n| n.children.contains(i)))
+                .collect(),
+        };
+        check_hierarchy(&nodes, &roots)?;
+
+        Ok(GltfScene {
+            meshes,
+            materials,
+            nodes,
+            roots,
+        })
+    }
+
+    pub fn meshes(&self) -> &[Arc<Mesh>] {
+        &self.meshes
+    }
+
+    pub fn materials(&self) -> &[PbrMaterial] {
+        &self.materials
+    }
+
+    pub fn nodes(&self) -> &[GltfNode] {
+        &self.nodes
+    }
+
+    /// The nodes of the scene that have no parent.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// The transform of every node relative to the scene, indexed like
+    /// [`GltfScene::nodes`]. Nodes outside of the scene keep their own
+    /// transform.
+    pub fn world_matrices(&self) -> Vec<Mat4> {
+        let mut world: Vec<Mat4> = self.nodes.iter().map(|n| n.transform).collect();
+        let mut stack: Vec<(usize, Mat4)> =
+            self.roots.iter().map(|r| (*r, Mat4::IDENTITY)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            world[node] = parent * self.nodes[node].transform;
+            for child in &self.nodes[node].children {
+                stack.push((*child, world[node]));
+            }
+        }
+        world
+    }
+
+    /// A model for every node of the scene that has a mesh. The node's world
+    /// transform becomes the model's [`Model::parent_matrix`], so shear from
+    /// non-uniform scale on a parent is kept, and the model's own
+    /// [`Model::transform`] starts out as the identity.
+    ///
+    /// Nodes that mirror their mesh share a copy of it with the faces turned
+    /// around, so that they aren't drawn inside out. Meshes with a
+    /// double-sided material are drawn with [`CullMode::None`].
+    pub fn models(&self) -> Vec<Model> {
+        let mut mirrored: Vec<Option<Arc<Mesh>>> = vec![None; self.meshes.len()];
+        self.instances()
+            .into_iter()
+            .map(|(mesh, world)| {
+                let shared = match world.determinant() < 0.0 {
+                    true => mirrored[mesh]
+                        .get_or_insert_with(|| Arc::new(reversed(&self.meshes[mesh])))
+                        .clone(),
+                    false => self.meshes[mesh].clone(),
+                };
+                let mut model = Model::from_mesh(shared);
+                model.set_parent_matrix(world);
+                if self.is_double_sided(mesh) {
+                    model.set_cull_mode(CullMode::None);
+                }
+                model
+            })
+            .collect()
+    }
+
+    // Whether any primitive of the mesh has a double-sided material
+    fn is_double_sided(&self, mesh: usize) -> bool {
+        self.meshes[mesh]
+            .sub_meshes
+            .iter()
+            .filter_map(|sub_mesh| sub_mesh.material)
+            .any(|material| self.materials[material].double_sided)
+    }
+
+    // Every mesh of the scene merged into one, with the world transforms of
+    // the nodes applied to the vertices, for `ModelBuilder`. `invert` false
+    // turns the faces back to the counter-clockwise order of the file.
+    pub(super) fn flattened(&self, invert: bool) -> Mesh {
+        let mut vertices: Vec<NormalVertex> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut tangents: Vec<[f32; 4]> = Vec::new();
+        let mut has_tangents = true;
+        let mut indices: Vec<u32> = Vec::new();
+        let mut sub_meshes: Vec<SubMesh> = Vec::new();
+        for (mesh, world) in self.instances() {
+            let mut mesh = Mesh::clone(&self.meshes[mesh]);
+            mesh.transform(world);
+            let flip = (world.determinant() < 0.0) == invert;
+            let first_vertex = vertices.len() as u32;
+            let corners: Vec<u32> = mesh
+                .geometry
+                .indices
+                .iter()
+                .map(|i| i as u32 + first_vertex)
+                .collect();
+            for sub_mesh in mesh.sub_meshes {
+                let first_index = indices.len() as u32;
+                let range = sub_mesh.indices.start as usize..sub_mesh.indices.end as usize;
+                for triangle in corners[range].chunks(3) {
+                    match flip {
+                        true => indices.extend([triangle[0], triangle[2], triangle[1]]),
+                        false => indices.extend(triangle),
+                    }
+                }
+                sub_meshes.push(SubMesh {
+                    indices: first_index..indices.len() as u32,
+                    ..sub_mesh
+                });
+            }
+            vertices.extend(mesh.geometry.vertices);
+            uvs.extend(mesh.uvs);
+            match mesh.tangents.is_empty() {
+                true => {
+                    has_tangents = false;
+                    tangents.resize(vertices.len(), [1.0, 0.0, 0.0, 1.0]);
+                }
+                false => tangents.extend(mesh.tangents),
+            }
+        }
+
+        if !has_tangents {
+            tangents.clear();
+        }
+        let vertex_count = vertices.len();
+        let (bounds, sphere) = mesh::bounds_of(&vertices);
+        Mesh {
+            bounds,
+            sphere,
+            geometry: IndexedMesh {
+                vertices,
+                indices: Indices::new(indices, vertex_count),
+            },
+            uvs,
+            tangents,
+            sub_meshes,
+            // All meshes share the materials of the file
+            materials: self
+                .meshes
+                .first()
+                .map(|m| m.materials.clone())
+                .unwrap_or_default(),
+            generation: 0,
+            id: mesh::MeshId::new(),
+        }
+    }
+
+    // The mesh index and world transform of every node of the scene that
+    // has a mesh, parents first.
+    fn instances(&self) -> Vec<(usize, Mat4)> {
+        let world = self.world_matrices();
+        let mut instances = Vec::new();
+        let mut stack: Vec<usize> = self.roots.iter().rev().copied().collect();
+        while let Some(node) = stack.pop() {
+            if let Some(mesh) = self.nodes[node].mesh {
+                instances.push((mesh, world[node]));
+            }
+            stack.extend(self.nodes[node].children.iter().rev());
+        }
+        instances
+    }
+}
+
+// `mesh` with the corners of every triangle in the opposite order
+fn reversed(mesh: &Mesh) -> Mesh {
+    let corners: Vec<u32> = mesh.geometry.indices.iter().map(|i| i as u32).collect();
+    let indices = corners
+        .chunks(3)
+        .flat_map(|triangle| [triangle[0], triangle[2], triangle[1]])
+        .collect();
+    let mut mesh = mesh.clone();
+    mesh.geometry.indices = Indices::new(indices, mesh.geometry.vertices.len());
+    mesh
+}
+
+// The walks over the node hierarchy only end if it is a tree below the
+// roots, as the specification requires, so files where a node is reached
+// twice are rejected.
+fn check_hierarchy(nodes: &[GltfNode], roots: &[usize]) -> Result<(), ObjError> {
+    let mut visited = vec![false; nodes.len()];
+    let mut stack = roots.to_vec();
+    while let Some(node) = stack.pop() {
+        if std::mem::replace(&mut visited[node], true) {
+            return Err(ObjError::unsupported(
+                "node hierarchy with a cycle or a shared node",
+            ));
+        }
+        stack.extend(&nodes[node].children);
+    }
+    Ok(())
+}
+
+fn import_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[buffer::Data],
+    materials: &[PbrMaterial],
+    mtl_materials: &[Material],
+) -> Result<Mesh, ObjError> {
+    let name = mesh.name().unwrap_or("").to_string();
+    let mut vertices: Vec<NormalVertex> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut tangents: Vec<[f32; 4]> = Vec::new();
+    let mut has_tangents = true;
+    let mut indices: Vec<u32> = Vec::new();
+    let mut sub_meshes: Vec<SubMesh> = Vec::new();
+
+    for primitive in mesh.primitives() {
+        if primitive.mode() != Mode::Triangles {
+            let feature = format!("primitive mode {:?}", primitive.mode());
+            return Err(ObjError::unsupported(&feature));
+        }
+        let reader = primitive.reader(|b| buffers.get(b.index()).map(|d| d.0.as_slice()));
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .ok_or_else(|| ObjError::unsupported("primitive without positions"))?
+            .collect();
+        let count = positions.len();
+        let mut corners: Vec<u32> = match reader.read_indices() {
+            Some(read) => read.into_u32().collect(),
+            None => (0..count as u32).collect(),
+        };
+        if corners.len() % 3 != 0 || corners.iter().any(|i| *i as usize >= count) {
+            return Err(ObjError::unsupported("primitive with invalid indices"));
+        }
+        let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|n| n.collect());
+        let mut primitive_uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+            Some(read) => read.into_f32().collect(),
+            None => vec![[0.0, 0.0]; count],
+        };
+        let mut primitive_tangents: Option<Vec<[f32; 4]>> =
+            reader.read_tangents().map(|t| t.collect());
+        let base_color = match primitive.material().index() {
+            Some(m) => materials[m].base_color,
+            None => [1.0; 4],
+        };
+        let mut colors: Vec<[f32; 3]> = match reader.read_colors(0) {
+            Some(read) => read
+                .into_rgb_f32()
+                .map(|c| {
+                    [
+                        c[0] * base_color[0],
+                        c[1] * base_color[1],
+                        c[2] * base_color[2],
+                    ]
+                })
+                .collect(),
+            None => vec![[base_color[0], base_color[1], base_color[2]]; count],
+        };
+
+        // Every attribute needs a value per position.
+        let lengths = [
+            ("NORMAL", normals.as_ref().map(Vec::len)),
+            ("TEXCOORD_0", Some(primitive_uvs.len())),
+            ("TANGENT", primitive_tangents.as_ref().map(Vec::len)),
+            ("COLOR_0", Some(colors.len())),
+        ];
+        for (attribute, length) in lengths {
+            if length.is_some_and(|length| length != count) {
+                let feature = format!(
+                    "{} attribute with a different count than POSITION",
+                    attribute
+                );
+                return Err(ObjError::unsupported(&feature));
+            }
+        }
+
+        // Flat normals need a vertex per corner.
+        let mut positions = positions;
+        let normals = match normals {
+            Some(normals) => normals,
+            None => {
+                let expand = |i: &u32| *i as usize;
+                positions = corners.iter().map(expand).map(|i| positions[i]).collect();
+                primitive_uvs = corners
+                    .iter()
+                    .map(expand)
+                    .map(|i| primitive_uvs[i])
+                    .collect();
+                colors = corners.iter().map(expand).map(|i| colors[i]).collect();
+                primitive_tangents =
+                    primitive_tangents.map(|t| corners.iter().map(expand).map(|i| t[i]).collect());
+                corners = (0..positions.len() as u32).collect();
+                flat_normals(&positions)
+            }
+        };
+
+        let first_vertex = vertices.len() as u32;
+        let first_index = indices.len() as u32;
+        vertices.extend(positions.iter().zip(&normals).zip(&colors).map(
+            |((position, normal), color)| NormalVertex {
+                position: *position,
+                normal: *normal,
+                color: *color,
+            },
+        ));
+        uvs.extend(primitive_uvs);
+        match primitive_tangents {
+            Some(t) => tangents.extend(t),
+            None => {
+                has_tangents = false;
+                tangents.resize(vertices.len(), [1.0, 0.0, 0.0, 1.0]);
+            }
+        }
+        // glTF faces are counter-clockwise, see `Winding`
+        for triangle in corners.chunks(3) {
+            indices.extend([triangle[0], triangle[2], triangle[1]].map(|i| i + first_vertex));
+        }
+        sub_meshes.push(SubMesh {
+            object: name.clone(),
+            group: String::new(),
+            material: primitive.material().index(),
+            indices: first_index..indices.len() as u32,
+            transform: Mat4::IDENTITY,
+            visible: true,
+        });
+    }
+
+    if !has_tangents {
+        tangents.clear();
+    }
+    let vertex_count = vertices.len();
+    let (bounds, sphere) = mesh::bounds_of(&vertices);
+    Ok(Mesh {
+        bounds,
+        sphere,
+        geometry: IndexedMesh {
+            vertices,
+            indices: Indices::new(indices, vertex_count),
+        },
+        uvs,
+        tangents,
+        sub_meshes,
+        materials: mtl_materials.to_vec(),
+        generation: 0,
+        id: mesh::MeshId::new(),
+    })
+}
+
+// The normal of every triangle of a non-indexed, counter-clockwise triangle
+// list, repeated for each of its corners.
+fn flat_normals(positions: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    positions
+        .chunks(3)
+        .flat_map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(Vec3::from);
+            [(b - a).cross(c - a).normalize_or_zero().to_array(); 3]
+        })
+        .collect()
+}
+
+fn pbr_material(
+    material: &gltf::Material,
+    buffers: &[buffer::Data],
+) -> Result<PbrMaterial, ObjError> {
+    let pbr = material.pbr_metallic_roughness();
+    let source = |texture: gltf::Texture| texture_source(&texture, buffers);
+    Ok(PbrMaterial {
+        name: material.name().unwrap_or("").to_string(),
+        base_color: pbr.base_color_factor(),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        emissive: material.emissive_factor(),
+        base_color_texture: pbr
+            .base_color_texture()
+            .map(|t| source(t.texture()))
+            .transpose()?,
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .map(|t| source(t.texture()))
+            .transpose()?,
+        normal_texture: material
+            .normal_texture()
+            .map(|t| source(t.texture()))
+            .transpose()?,
+        occlusion_texture: material
+            .occlusion_texture()
+            .map(|t| source(t.texture()))
+            .transpose()?,
+        emissive_texture: material
+            .emissive_texture()
+            .map(|t| source(t.texture()))
+            .transpose()?,
+        alpha_cutoff: match material.alpha_mode() {
+            gltf::material::AlphaMode::Mask => Some(material.alpha_cutoff().unwrap_or(0.5)),
+            _ => None,
+        },
+        blend: material.alpha_mode() == gltf::material::AlphaMode::Blend,
+        double_sided: material.double_sided(),
+    })
+}
+
+fn texture_source(
+    texture: &gltf::Texture,
+    buffers: &[buffer::Data],
+) -> Result<TextureSource, ObjError> {
+    match texture.source().source() {
+        gltf::image::Source::Uri { uri, .. } => Ok(TextureSource::Uri(uri.to_string())),
+        gltf::image::Source::View { view, mime_type } => {
+            let data = buffers.get(view.buffer().index()).and_then(|buffer| {
+                let end = view.offset().checked_add(view.length())?;
+                buffer.0.get(view.offset()..end)
+            });
+            match data {
+                Some(data) => Ok(TextureSource::Embedded {
+                    mime_type: mime_type.to_string(),
+                    data: data.to_vec(),
+                }),
+                None => Err(ObjError::unsupported("image view outside of its buffer")),
+            }
+        }
+    }
+}
+
+// The closest obj material, used for the vertex colors and by the obj
+// exporter.
+fn to_mtl_material(pbr: &PbrMaterial) -> Material {
+    let [r, g, b, a] = pbr.base_color;
+    let uri = |texture: &Option<TextureSource>| match texture {
+        Some(TextureSource::Uri(uri)) => Some(uri.clone()),
+        _ => None,
+    };
+    Material {
+        diffuse: [r, g, b],
+        emissive: pbr.emissive,
+        dissolve: a,
+        diffuse_map: uri(&pbr.base_color_texture),
+        emissive_map: uri(&pbr.emissive_texture),
+        bump_map: uri(&pbr.normal_texture),
+        ..Material::new(&pbr.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::{Mat4, Quat, Vec3};
+
+    use super::GltfScene;
+    use crate::obj_loader::{CullMode, Mesh, ObjError};
+
+    // A glb file with a single triangle facing +Z, counter-clockwise, and
+    // `nodes` added to the JSON chunk.
+    fn glb(nodes: &str) -> Vec<u8> {
+        glb_with_primitive("", nodes)
+    }
+
+    // Same as `glb`, with `primitive` added to the JSON of the primitive.
+    fn glb_with_primitive(primitive: &str, nodes: &str) -> Vec<u8> {
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},
+            "buffers":[{{"byteLength":72}}],
+            "bufferViews":[{{"buffer":0,"byteLength":36}},{{"buffer":0,"byteOffset":36,"byteLength":36}}],
+            "accessors":[
+                {{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3","min":[0,0,0],"max":[1,1,0]}},
+                {{"bufferView":1,"componentType":5126,"count":3,"type":"VEC3"}}],
+            "meshes":[{{"primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1}}{primitive}}}]}}],
+            {nodes}}}"#
+        );
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin: Vec<u8> = Vec::new();
+        for position in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            position.iter().for_each(|x| bin.extend(x.to_le_bytes()));
+        }
+        for _ in 0..3 {
+            [0.0f32, 0.0, 1.0]
+                .iter()
+                .for_each(|x| bin.extend(x.to_le_bytes()));
+        }
+
+        let mut glb: Vec<u8> = Vec::new();
+        glb.extend(b"glTF");
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(b"JSON");
+        glb.extend(json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(bin);
+        glb
+    }
+
+    // Whether every face is wound counter-clockwise around its normals.
+    fn counter_clockwise(mesh: &Mesh) -> Vec<bool> {
+        let corners: Vec<_> = mesh.geometry.indices.iter().collect();
+        corners
+            .chunks(3)
+            .map(|c| {
+                let [a, b, c] = [c[0], c[1], c[2]].map(|i| mesh.geometry.vertices[i]);
+                let p = |v: [f32; 3]| Vec3::from(v);
+                let face = (p(b.position) - p(a.position)).cross(p(c.position) - p(a.position));
+                face.dot(p(a.normal) + p(b.normal) + p(c.normal)) > 0.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn node_transforms_are_combined() {
+        let rotation = Quat::from_rotation_z(0.5);
+        let nodes = format!(
+            r#""nodes":[
+                {{"translation":[1,2,3],"children":[1]}},
+                {{"mesh":0,"scale":[2,2,2],"rotation":[{},{},{},{}]}},
+                {{"mesh":0,"matrix":[1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,5,1]}},
+                {{"mesh":0}}],
+            "scenes":[{{"nodes":[0,2]}}],"scene":0"#,
+            rotation.x, rotation.y, rotation.z, rotation.w
+        );
+        let scene = GltfScene::from_slice(&glb(&nodes), None).unwrap();
+        assert_eq!(scene.roots(), [0, 2]);
+
+        let child = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0))
+            * Mat4::from_scale_rotation_translation(Vec3::splat(2.0), rotation, Vec3::ZERO);
+        let world = scene.world_matrices();
+        assert!(world[1].abs_diff_eq(child, 1e-6));
+        assert_eq!(world[2], Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)));
+        // Node 3 is outside of the scene.
+        assert_eq!(world[3], Mat4::IDENTITY);
+
+        let models = scene.models();
+        assert_eq!(models.len(), 2);
+        assert!(models[0].model_matrix().abs_diff_eq(child, 1e-6));
+        assert_eq!(models[1].model_matrix(), world[2]);
+
+        let flattened = scene.flattened(true);
+        let position = Vec3::from(flattened.geometry.vertices[1].position);
+        assert!(position.abs_diff_eq(child.transform_point3(Vec3::X), 1e-5));
+        assert_eq!(flattened.sub_meshes.len(), 2);
+    }
+
+    #[test]
+    fn flattening_keeps_mirrored_nodes_facing_out() {
+        let nodes = r#""nodes":[{"mesh":0},{"mesh":0,"scale":[-1,1,1]}]"#;
+        let scene = GltfScene::from_slice(&glb(nodes), None).unwrap();
+        // The imported mesh is clockwise, like the default of the builder.
+        assert_eq!(counter_clockwise(&scene.meshes()[0]), [false]);
+
+        // Mirroring turns the second copy around, which its corners undo.
+        assert_eq!(counter_clockwise(&scene.flattened(true)), [false, false]);
+        assert_eq!(counter_clockwise(&scene.flattened(false)), [true, true]);
+        let mirrored = scene.flattened(true);
+        let corners: Vec<_> = mirrored.geometry.indices.iter().collect();
+        let imported: Vec<_> = scene.meshes()[0].geometry.indices.iter().collect();
+        assert_eq!(corners[..3], imported[..]);
+        assert_eq!(
+            corners[3..],
+            [imported[0] + 3, imported[2] + 3, imported[1] + 3]
+        );
+    }
+
+    #[test]
+    fn mirrored_models_share_a_turned_around_mesh() {
+        let nodes =
+            r#""nodes":[{"mesh":0},{"mesh":0,"scale":[-1,1,1]},{"mesh":0,"scale":[1,-1,1]}]"#;
+        let scene = GltfScene::from_slice(&glb(nodes), None).unwrap();
+        let models = scene.models();
+        assert!(Arc::ptr_eq(models[0].shared_mesh(), &scene.meshes()[0]));
+        assert!(Arc::ptr_eq(
+            models[1].shared_mesh(),
+            models[2].shared_mesh()
+        ));
+        assert!(!Arc::ptr_eq(
+            models[0].shared_mesh(),
+            models[1].shared_mesh()
+        ));
+
+        // In world space, the mirrored copies turn like the original.
+        for model in &models {
+            let mesh = model.shared_mesh();
+            let corners: Vec<Vec3> = mesh
+                .geometry
+                .indices
+                .iter()
+                .map(|i| Vec3::from(mesh.geometry.vertices[i].position))
+                .map(|p| model.model_matrix().transform_point3(p))
+                .collect();
+            let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+            assert!(normal.z < 0.0, "{normal}");
+            assert_eq!(model.cull_mode(), CullMode::Back);
+        }
+    }
+
+    #[test]
+    fn double_sided_materials_are_not_culled() {
+        for (double_sided, cull_mode) in [(true, CullMode::None), (false, CullMode::Back)] {
+            let nodes =
+                format!(r#""materials":[{{"doubleSided":{double_sided}}}],"nodes":[{{"mesh":0}}]"#);
+            let scene =
+                GltfScene::from_slice(&glb_with_primitive(r#","material":0"#, &nodes), None)
+                    .unwrap();
+            assert_eq!(scene.materials()[0].double_sided, double_sided);
+            assert_eq!(scene.models()[0].cull_mode(), cull_mode);
+        }
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        for nodes in [
+            r#""nodes":[{"children":[1]},{"mesh":0,"children":[0]}],"scenes":[{"nodes":[0]}]"#,
+            r#""nodes":[{"children":[2]},{"children":[2]},{"mesh":0}],"scenes":[{"nodes":[0,1]}]"#,
+        ] {
+            let error = GltfScene::from_slice(&glb(nodes), None).err().unwrap();
+            assert!(
+                matches!(&error, ObjError::Unsupported { path, .. } if path == "<slice>"),
+                "{error}"
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::BufRead;
+
+use super::{ObjError, Resolver};
+
+/// A material read from an mtl library.
+///
+/// Texture maps are stored exactly as written in the file. Use
+/// [`Material::open_map`] to open them with the [`Resolver`] the library was
+/// read with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// The resolved name of the mtl library the material was read from,
+    /// which texture maps are resolved against. Empty for other materials.
+    pub library: String,
+    /// `Ka`
+    pub ambient: [f32; 3],
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ke`
+    pub emissive: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, or `1 - Tr`
+    pub dissolve: f32,
+    /// `illum`
+    pub illumination_model: u32,
+    /// `map_Ka`
+    pub ambient_map: Option<String>,
+    /// `map_Kd`
+    pub diffuse_map: Option<String>,
+    /// `map_Ks`
+    pub specular_map: Option<String>,
+    /// `map_Ke`
+    pub emissive_map: Option<String>,
+    /// `map_Ns`
+    pub shininess_map: Option<String>,
+    /// `map_d`
+    pub dissolve_map: Option<String>,
+    /// `map_Bump` or `bump`
+    pub bump_map: Option<String>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Material {
+        Material {
+            name: name.to_string(),
+            library: String::new(),
+            ambient: [0.0; 3],
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            illumination_model: 0,
+            ambient_map: None,
+            diffuse_map: None,
+            specular_map: None,
+            emissive_map: None,
+            shininess_map: None,
+            dissolve_map: None,
+            bump_map: None,
+        }
+    }
+
+    /// Reads every material of the mtl library `file_name`, a name already
+    /// returned by [`Resolver::resolve`], opened with `resolver`.
+    pub fn load_library(
+        file_name: &str,
+        resolver: &dyn Resolver,
+    ) -> Result<Vec<Material>, ObjError> {
+        let input = resolver.open(file_name).map_err(|source| ObjError::Io {
+            path: file_name.to_string(),
+            source,
+        })?;
+        Material::parse_library(input, file_name)
+    }
+
+    /// Reads every material of an mtl library. `file_name` is used to report
+    /// errors and becomes the [`Material::library`] of every material.
+    pub fn parse_library(input: impl BufRead, file_name: &str) -> Result<Vec<Material>, ObjError> {
+        let mut materials: Vec<Material> = Vec::new();
+        for (line_index, raw_line) in input.lines().enumerate() {
+            let line = raw_line.map_err(|source| ObjError::Io {
+                path: file_name.to_string(),
+                source,
+            })?;
+            let line = line.trim();
+            let (keyword, x) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let x = x.trim();
+            if keyword.is_empty() || keyword.starts_with('#') {
+                continue;
+            }
+            if keyword == "newmtl" {
+                materials.push(Material {
+                    library: file_name.to_string(),
+                    ..Material::new(x)
+                });
+                continue;
+            }
+            let parsed = match materials.last_mut() {
+                Some(material) => material.parse_statement(keyword, x),
+                None => Err(ObjError::malformed(line)),
+            };
+            parsed.map_err(|e| e.at(file_name, line_index + 1))?;
+        }
+        Ok(materials)
+    }
+
+    /// Resolves `map`, one of the texture maps of this material, against
+    /// the library the material was read from.
+    pub fn resolve_map(&self, map: &str, resolver: &dyn Resolver) -> String {
+        resolver.resolve(&self.library, map)
+    }
+
+    /// Opens `map`, one of the texture maps of this material, relative to
+    /// the library the material was read from.
+    pub fn open_map(
+        &self,
+        map: &str,
+        resolver: &dyn Resolver,
+    ) -> Result<Box<dyn BufRead>, ObjError> {
+        let path = self.resolve_map(map, resolver);
+        resolver
+            .open(&path)
+            .map_err(|source| ObjError::Io { path, source })
+    }
+
+    pub(super) fn maps(&self) -> [&Option<String>; 7] {
+        [
+            &self.ambient_map,
+            &self.diffuse_map,
+            &self.specular_map,
+            &self.emissive_map,
+            &self.shininess_map,
+            &self.dissolve_map,
+            &self.bump_map,
+        ]
+    }
+
+    pub(super) fn maps_mut(&mut self) -> [&mut Option<String>; 7] {
+        [
+            &mut self.ambient_map,
+            &mut self.diffuse_map,
+            &mut self.specular_map,
+            &mut self.emissive_map,
+            &mut self.shininess_map,
+            &mut self.dissolve_map,
+            &mut self.bump_map,
+        ]
+    }
+
+    fn parse_statement(&mut self, keyword: &str, x: &str) -> Result<(), ObjError> {
+        match keyword {
+            "Ka" => self.ambient = parse_color(x)?,
+            "Kd" => self.diffuse = parse_color(x)?,
+            "Ks" => self.specular = parse_color(x)?,
+            "Ke" => self.emissive = parse_color(x)?,
+            "Ns" => self.shininess = parse_float(x)?,
+            "d" => self.dissolve = parse_float(x)?,
+            "Tr" => self.dissolve = 1.0 - parse_float(x)?,
+            "illum" => {
+                self.illumination_model = x.parse().map_err(|_| ObjError::invalid_number(x))?
+            }
+            "map_Ka" => self.ambient_map = Some(parse_map(x)?),
+            "map_Kd" => self.diffuse_map = Some(parse_map(x)?),
+            "map_Ks" => self.specular_map = Some(parse_map(x)?),
+            "map_Ke" => self.emissive_map = Some(parse_map(x)?),
+            "map_Ns" => self.shininess_map = Some(parse_map(x)?),
+            "map_d" => self.dissolve_map = Some(parse_map(x)?),
+            "map_Bump" | "map_bump" | "bump" => self.bump_map = Some(parse_map(x)?),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_float(x: &str) -> Result<f32, ObjError> {
+    x.parse().map_err(|_| ObjError::invalid_number(x))
+}
+
+// A single value is shorthand for a grey of that intensity.
+fn parse_color(x: &str) -> Result<[f32; 3], ObjError> {
+    let values = x
+        .split_whitespace()
+        .map(parse_float)
+        .collect::<Result<Vec<f32>, ObjError>>()?;
+    match values.as_slice() {
+        [v] => Ok([*v; 3]),
+        [r, g, b] => Ok([*r, *g, *b]),
+        _ => Err(ObjError::malformed(x)),
+    }
+}
+
+// Texture statements may carry options such as `-s 1 1 1` before the file
+// name, which is always the last token.
+fn parse_map(x: &str) -> Result<String, ObjError> {
+    x.split_whitespace()
+        .last()
+        .map(str::to_string)
+        .ok_or_else(|| ObjError::malformed(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(mtl: &str) -> Result<Vec<Material>, ObjError> {
+        Material::parse_library(mtl.as_bytes(), "test.mtl")
+    }
+
+    #[test]
+    fn colors_take_one_or_three_values() {
+        let materials = parse(
+            "newmtl grey\nKa 0.25\nKd 0.5\nKs 1 0.5 0\n\
+             newmtl broken\nKd 1 0\n",
+        );
+        assert!(matches!(
+            materials,
+            Err(ObjError::Malformed { line: 6, .. })
+        ));
+
+        let materials = parse("newmtl grey\nKa 0.25\nKd 0.5\nKs 1 0.5 0\nKe 0 0 1\n").unwrap();
+        let grey = &materials[0];
+        assert_eq!(grey.name, "grey");
+        assert_eq!(grey.library, "test.mtl");
+        assert_eq!(grey.ambient, [0.25; 3]);
+        assert_eq!(grey.diffuse, [0.5; 3]);
+        assert_eq!(grey.specular, [1.0, 0.5, 0.0]);
+        assert_eq!(grey.emissive, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn transparency_is_the_inverse_of_dissolve() {
+        let materials = parse(
+            "newmtl opaque\nKd 1 1 1\n\
+             newmtl dissolved\nd 0.25\n\
+             newmtl transparent\nTr 0.25\n\
+             newmtl both\nTr 0.25\nd 0.5\n",
+        )
+        .unwrap();
+        let dissolve: Vec<f32> = materials.iter().map(|m| m.dissolve).collect();
+        // The statement that comes last wins.
+        assert_eq!(dissolve, [1.0, 0.25, 0.75, 0.5]);
+    }
+
+    #[test]
+    fn illumination_models_are_integers() {
+        let materials = parse("newmtl shiny\nillum 2\nNs 96\n").unwrap();
+        assert_eq!(materials[0].illumination_model, 2);
+        assert_eq!(materials[0].shininess, 96.0);
+
+        let error = parse("newmtl shiny\nillum 2.5\n").err().unwrap();
+        assert!(matches!(
+            error,
+            ObjError::InvalidNumber { line: 2, token, .. } if token == "2.5"
+        ));
+    }
+
+    #[test]
+    fn map_options_before_the_file_name_are_skipped() {
+        let materials = parse(
+            "newmtl textured\n\
+             map_Kd -s 2 2 1 -o 0.5 0.5 0 wood.png\n\
+             map_Ks specular.png\n\
+             bump -bm 0.5 normal.png\n\
+             map_d -clamp on alpha.png\n",
+        )
+        .unwrap();
+        let textured = &materials[0];
+        assert_eq!(textured.diffuse_map.as_deref(), Some("wood.png"));
+        assert_eq!(textured.specular_map.as_deref(), Some("specular.png"));
+        assert_eq!(textured.bump_map.as_deref(), Some("normal.png"));
+        assert_eq!(textured.dissolve_map.as_deref(), Some("alpha.png"));
+        assert_eq!(textured.ambient_map, None);
+
+        assert!(matches!(
+            parse("newmtl textured\nmap_Kd\n"),
+            Err(ObjError::Malformed { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn statements_before_the_first_material_are_errors() {
+        let materials = parse("# colors\n\nnewmtl red\nKd 1 0 0\nunknown 1\n").unwrap();
+        assert_eq!(materials.len(), 1);
+        assert!(matches!(
+            parse("Kd 1 0 0\nnewmtl red\n"),
+            Err(ObjError::Malformed { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytemuck::Pod;
+use glam::{Mat4, Vec3};
+
+use super::{
+    winding, Aabb, ColoredVertex, Material, NormalVertex, Sphere, TexturedNormalVertex, Winding,
+};
+
+/// The index data of an [`IndexedMesh`].
+///
+/// 16-bit indices are used whenever every vertex of the mesh can be addressed
+/// with them, which halves the size of the index buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks the smallest index type able to address `vertex_count` vertices.
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Indices {
+        if vertex_count <= u16::MAX as usize + 1 {
+            Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(x) => x.len(),
+            Indices::U32(x) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<usize> {
+        match self {
+            Indices::U16(x) => x.get(index).map(|i| *i as usize),
+            Indices::U32(x) => x.get(index).map(|i| *i as usize),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(|i| self.get(i).unwrap())
+    }
+}
+
+/// A triangle list stored as unique vertices plus indices into them.
+#[derive(Clone, Debug)]
+pub struct IndexedMesh<V = NormalVertex> {
+    pub vertices: Vec<V>,
+    pub indices: Indices,
+}
+
+impl<V: Pod> IndexedMesh<V> {
+    /// Builds an indexed mesh from a non-indexed triangle list. Vertices
+    /// that are bit-for-bit identical are merged into one.
+    pub fn from_triangle_list(list: &[V]) -> IndexedMesh<V> {
+        let mut vertices: Vec<V> = Vec::new();
+        let mut seen: HashMap<&[u8], u32> = HashMap::new();
+        let indices = list
+            .iter()
+            .map(|v| {
+                *seen.entry(bytemuck::bytes_of(v)).or_insert_with(|| {
+                    vertices.push(*v);
+                    vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+        let vertex_count = vertices.len();
+        IndexedMesh {
+            vertices,
+            indices: Indices::new(indices, vertex_count),
+        }
+    }
+
+    /// Expands the mesh back into a non-indexed triangle list.
+    pub fn to_triangle_list(&self) -> Vec<V> {
+        self.indices.iter().map(|i| self.vertices[i]).collect()
+    }
+}
+
+/// A named part of a model: the triangles of one object or group of an obj
+/// file that share a material.
+#[derive(Clone, Debug)]
+pub struct SubMesh {
+    /// The name given by the last `o` statement, empty if there was none.
+    pub object: String,
+    /// The name given by the last `g` statement, empty if there was none.
+    pub group: String,
+    /// Index into the materials of the model.
+    pub material: Option<usize>,
+    /// The part of the model's index buffer that makes up this sub-mesh.
+    pub indices: Range<u32>,
+    /// Applied in model space, before the model matrix.
+    pub transform: Mat4,
+    pub visible: bool,
+}
+
+impl SubMesh {
+    /// Whether `name` is either the object or the group name of this
+    /// sub-mesh.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.object == name || self.group == name
+    }
+}
+
+// Tells meshes apart for as long as the program runs. A clone is a
+// different mesh, so it gets a new id.
+#[derive(Debug)]
+pub(super) struct MeshId(u64);
+
+impl MeshId {
+    pub(super) fn new() -> MeshId {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        MeshId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Clone for MeshId {
+    fn clone(&self) -> MeshId {
+        MeshId::new()
+    }
+}
+
+/// The geometry and materials of a loaded model, shared by every [`Model`]
+/// made from it through an `Arc`.
+///
+/// [`Model`]: super::Model
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub(super) geometry: IndexedMesh,
+    // The texture coordinates of each vertex of `geometry`
+    pub(super) uvs: Vec<[f32; 2]>,
+    // The tangent of each vertex of `geometry` with the handedness of the
+    // bitangent in `w`, empty if the source had none
+    pub(super) tangents: Vec<[f32; 4]>,
+    pub(super) sub_meshes: Vec<SubMesh>,
+    pub(super) materials: Vec<Material>,
+    pub(super) bounds: Aabb,
+    pub(super) sphere: Sphere,
+    pub(super) generation: u64,
+    pub(super) id: MeshId,
+}
+
+impl Mesh {
+    /// The deduplicated vertices and indices.
+    pub fn geometry(&self) -> &IndexedMesh {
+        &self.geometry
+    }
+
+    /// The vertices of [`Mesh::geometry`] with texture coordinates instead
+    /// of colors. They share its index buffer.
+    pub fn textured_vertices(&self) -> Vec<TexturedNormalVertex> {
+        self.geometry
+            .vertices
+            .iter()
+            .zip(&self.uvs)
+            .map(|(v, uv)| TexturedNormalVertex {
+                position: v.position,
+                normal: v.normal,
+                uv: *uv,
+            })
+            .collect()
+    }
+
+    /// The vertices of [`Mesh::geometry`] without normals. They share its
+    /// index buffer.
+    pub fn colored_vertices(&self) -> Vec<ColoredVertex> {
+        self.geometry
+            .vertices
+            .iter()
+            .map(|v| ColoredVertex {
+                position: v.position,
+                color: v.color,
+            })
+            .collect()
+    }
+
+    /// The tangent of every vertex of [`Mesh::geometry`], with the sign of
+    /// the bitangent in the last component. Empty for sources without
+    /// tangents, such as obj files.
+    pub fn tangents(&self) -> &[[f32; 4]] {
+        &self.tangents
+    }
+
+    /// The winding order of the faces as they are stored, guessed from the
+    /// vertex normals like the loaders do for files. Loaded meshes are
+    /// normally [`Winding::Clockwise`], the order the renderer draws.
+    pub fn winding_order(&self) -> Option<Winding> {
+        let positions: Vec<Vec3> = self
+            .geometry
+            .vertices
+            .iter()
+            .map(|v| Vec3::from(v.position))
+            .collect();
+        let corners: Vec<usize> = self.geometry.indices.iter().collect();
+        let triangles: Vec<[usize; 3]> = corners.chunks(3).map(|t| [t[0], t[1], t[2]]).collect();
+        let normals: Vec<Option<Vec3>> = triangles
+            .iter()
+            .map(|t| {
+                let normal: Vec3 = t
+                    .iter()
+                    .map(|i| Vec3::from(self.geometry.vertices[*i].normal))
+                    .sum();
+                Some(normal).filter(|n| *n != Vec3::ZERO)
+            })
+            .collect();
+        winding::detect(&positions, &triangles, &normals)
+    }
+
+    /// The parts of the mesh as loaded, visible and without transforms.
+    pub fn sub_meshes(&self) -> &[SubMesh] {
+        &self.sub_meshes
+    }
+
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
+    }
+
+    /// The bounding box of the mesh in model space.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// The bounding sphere of the mesh in model space.
+    pub fn bounding_sphere(&self) -> Sphere {
+        self.sphere
+    }
+
+    /// A number no other mesh of the program has, including copies of this
+    /// one, e.g. those made by [`super::Model::edit_mesh`] when the mesh is
+    /// shared.
+    pub fn id(&self) -> u64 {
+        self.id.0
+    }
+
+    /// Counts the changes made through [`super::Model::edit_mesh`], so that
+    /// copies of the geometry, e.g. on the GPU, know when to update.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Moves the vertices by an affine `transform` and updates the bounds.
+    // Transforms that mirror the mesh also flip its winding order, which is
+    // left to the caller.
+    pub(super) fn transform(&mut self, transform: Mat4) {
+        let normal_matrix = transform.inverse().transpose();
+        for vertex in &mut self.geometry.vertices {
+            let position = transform.transform_point3(Vec3::from(vertex.position));
+            let normal = normal_matrix.transform_vector3(Vec3::from(vertex.normal));
+            vertex.position = position.to_array();
+            vertex.normal = normal.normalize_or_zero().to_array();
+        }
+        let handedness = transform.determinant().signum();
+        for tangent in &mut self.tangents {
+            let direction = transform.transform_vector3(Vec3::from_slice(tangent));
+            *tangent = direction
+                .normalize_or_zero()
+                .extend(tangent[3] * handedness)
+                .into();
+        }
+        (self.bounds, self.sphere) = bounds_of(&self.geometry.vertices);
+    }
+}
+
+// The bounding box and sphere of `vertices`.
+pub(super) fn bounds_of(vertices: &[NormalVertex]) -> (Aabb, Sphere) {
+    let points = || vertices.iter().map(|v| Vec3::from(v.position));
+    (Aabb::from_points(points()), Sphere::from_points(points()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32) -> NormalVertex {
+        NormalVertex {
+            position: [x, 0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            color: [1.0; 3],
+        }
+    }
+
+    #[test]
+    fn identical_vertices_are_merged() {
+        let list = [0.0, 1.0, 2.0, 2.0, 1.0, 3.0].map(vertex);
+        let mesh = IndexedMesh::from_triangle_list(&list);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, Indices::U16(vec![0, 1, 2, 2, 1, 3]));
+        let expanded = mesh.to_triangle_list();
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&expanded),
+            bytemuck::cast_slice::<_, u8>(&list)
+        );
+
+        // Only byte-identical vertices are merged.
+        let mut recolored = vertex(1.0);
+        recolored.color[0] = 0.5;
+        let list = [vertex(0.0), vertex(1.0), recolored, vertex(-0.0)];
+        assert_eq!(IndexedMesh::from_triangle_list(&list).vertices.len(), 4);
+    }
+
+    #[test]
+    fn indices_are_16_bit_while_every_vertex_fits() {
+        let largest = u16::MAX as usize + 1;
+        let list: Vec<NormalVertex> = (0..largest).map(|i| vertex(i as f32)).collect();
+        let mesh = IndexedMesh::from_triangle_list(&list);
+        assert_eq!(mesh.vertices.len(), largest);
+        assert!(matches!(&mesh.indices, Indices::U16(indices) if indices[largest - 1] == u16::MAX));
+
+        let list: Vec<NormalVertex> = (0..largest + 1).map(|i| vertex(i as f32)).collect();
+        let mesh = IndexedMesh::from_triangle_list(&list);
+        assert!(
+            matches!(&mesh.indices, Indices::U32(indices) if indices[largest] == largest as u32)
+        );
+        assert_eq!(mesh.indices.get(largest), Some(largest));
+        assert_eq!(mesh.indices.len(), largest + 1);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+/// How the face normals around a shared vertex are weighted when they are
+/// averaged into a smooth vertex normal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Larger faces pull the normal further towards their own normal.
+    Area,
+    /// Each face contributes in proportion to its corner angle at the
+    /// vertex, which keeps the result independent of how a surface happens
+    /// to be tessellated.
+    #[default]
+    Angle,
+}
+
+/// Computes one normal per triangle corner.
+///
+/// Triangles in smoothing group 0 get flat normals. All other triangles get
+/// the weighted average of the face normals of every triangle in the same
+/// smoothing group that shares the vertex. When `crease_angle` is set, faces
+/// whose normals differ by more than that many radians are not averaged
+/// together, so hard edges stay hard even inside one group.
+///
+/// Normals point to the side the triangles are counter-clockwise from, or
+/// with `flip` to the side they are clockwise from, which is the side the
+/// renderer draws.
+pub(super) fn generate(
+    positions: &[Vec3],
+    triangles: &[[usize; 3]],
+    groups: &[u32],
+    flip: bool,
+    weighting: NormalWeighting,
+    crease_angle: Option<f32>,
+) -> Vec<[Vec3; 3]> {
+    let face_normals: Vec<Vec3> = triangles
+        .iter()
+        .map(|t| {
+            let [a, b, c] = t.map(|i| positions[i]);
+            let normal = (b - a).cross(c - a);
+            if flip {
+                -normal
+            } else {
+                normal
+            }
+        })
+        .collect();
+    let unit_normals: Vec<Vec3> = face_normals.iter().map(|n| n.normalize_or_zero()).collect();
+
+    // Every corner that belongs to a smoothing group, keyed by the vertex
+    // and the group, together with its weighted contribution.
+    let mut shared: HashMap<(usize, u32), Vec<(usize, Vec3)>> = HashMap::new();
+    for (face, triangle) in triangles.iter().enumerate() {
+        if groups[face] == 0 {
+            continue;
+        }
+        for corner in 0..3 {
+            let contribution = match weighting {
+                NormalWeighting::Area => face_normals[face],
+                NormalWeighting::Angle => {
+                    unit_normals[face] * corner_angle(positions, triangle, corner)
+                }
+            };
+            shared
+                .entry((triangle[corner], groups[face]))
+                .or_default()
+                .push((face, contribution));
+        }
+    }
+
+    let min_cos = crease_angle.map(f32::cos);
+    triangles
+        .iter()
+        .enumerate()
+        .map(|(face, triangle)| {
+            let flat = unit_normals[face];
+            if groups[face] == 0 {
+                return [flat; 3];
+            }
+            triangle.map(|vertex| {
+                let sum: Vec3 = shared[&(vertex, groups[face])]
+                    .iter()
+                    .filter(|(other, _)| {
+                        min_cos.is_none_or(|min| unit_normals[*other].dot(flat) >= min)
+                    })
+                    .map(|(_, contribution)| *contribution)
+                    .sum();
+                sum.try_normalize().unwrap_or(flat)
+            })
+        })
+        .collect()
+}
+
+fn corner_angle(positions: &[Vec3], triangle: &[usize; 3], corner: usize) -> f32 {
+    let at = positions[triangle[corner]];
+    let to_next = positions[triangle[(corner + 1) % 3]] - at;
+    let to_prev = positions[triangle[(corner + 2) % 3]] - at;
+    match (to_next.try_normalize(), to_prev.try_normalize()) {
+        (Some(a), Some(b)) => a.dot(b).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,176 @@
+use glam::{Mat4, Vec3};
+
+use super::Mesh;
+
+/// The point of a model that [`super::ModelBuilder::recenter`] moves to the
+/// origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recenter {
+    /// The center of the bounding box.
+    BoundsCenter,
+    /// The center of mass of the surface, which isn't thrown off by a few
+    /// outlying vertices.
+    Centroid,
+}
+
+/// The size [`super::ModelBuilder::normalize`] scales a model to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Normalize {
+    /// The longest side of the bounding box becomes 1, so a model centered
+    /// on its bounding box fits between -0.5 and 0.5 on every axis.
+    UnitCube,
+    /// The vertex farthest from the origin ends up at a distance of 1.
+    UnitSphere,
+}
+
+/// The axis pointing up in a model file. Models are converted to the
+/// Y-up convention of the renderer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    /// Like Blender's obj export and glTF.
+    #[default]
+    Y,
+    /// Like most CAD software and 3D scanners.
+    Z,
+}
+
+// Applies the options of the builder in order: the up-axis, then the
+// recentering, then the scale.
+pub(super) fn apply(
+    mesh: &mut Mesh,
+    up_axis: UpAxis,
+    recenter: Option<Recenter>,
+    normalize: Option<Normalize>,
+) {
+    if up_axis == UpAxis::Z {
+        // +Z becomes +Y and +Y becomes -Z, keeping the handedness
+        mesh.transform(Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2));
+    }
+    if let Some(recenter) = recenter {
+        let center = match recenter {
+            Recenter::BoundsCenter => mesh.bounds.center(),
+            Recenter::Centroid => centroid(mesh),
+        };
+        mesh.transform(Mat4::from_translation(-center));
+    }
+    if let Some(normalize) = normalize {
+        let size = match normalize {
+            Normalize::UnitCube => mesh.bounds.size().max_element(),
+            Normalize::UnitSphere => mesh
+                .geometry
+                .vertices
+                .iter()
+                .map(|v| Vec3::from(v.position).length())
+                .fold(0.0, f32::max),
+        };
+        // A single point or an empty mesh can't be scaled to a size.
+        if size > 0.0 {
+            mesh.transform(Mat4::from_scale(Vec3::splat(1.0 / size)));
+        }
+    }
+}
+
+// The area-weighted average of the triangle centers, or the average vertex
+// if every triangle is degenerate.
+fn centroid(mesh: &Mesh) -> Vec3 {
+    let geometry = &mesh.geometry;
+    let position = |i: usize| Vec3::from(geometry.vertices[i].position);
+    let (mut weighted, mut area) = (Vec3::ZERO, 0.0);
+    for first in (0..geometry.indices.len()).step_by(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| position(geometry.indices.get(first + i).unwrap()));
+        let triangle_area = (b - a).cross(c - a).length() * 0.5;
+        weighted += (a + b + c) / 3.0 * triangle_area;
+        area += triangle_area;
+    }
+    if area > 0.0 {
+        return weighted / area;
+    }
+    let count = geometry.vertices.len().max(1) as f32;
+    (0..geometry.vertices.len()).map(position).sum::<Vec3>() / count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj_loader::Model;
+
+    fn mesh(obj: &str) -> Mesh {
+        Model::from_obj_str(obj).build_mesh().unwrap()
+    }
+
+    fn positions(mesh: &Mesh) -> Vec<Vec3> {
+        let vertices = &mesh.geometry.vertices;
+        vertices.iter().map(|v| Vec3::from(v.position)).collect()
+    }
+
+    // A right triangle with legs of 4 along X and 2 along Y, and a tiny one
+    // far along X that moves the bounding box but barely the centroid.
+    const TRIANGLES: &str = "v 0 0 0\nv 4 0 0\nv 0 2 0\n\
+                             v 10 0 0\nv 10.1 0 0\nv 10 0.1 0\nf 1 2 3\nf 4 5 6\n";
+
+    #[test]
+    fn z_up_models_are_turned_to_y_up() {
+        // Standing 2 high along Z, facing -Y
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 0 2\nvn 0 -1 0\nf 1//1 2//1 3//1\n";
+        let original = mesh(obj);
+        let mut turned = original.clone();
+        apply(&mut turned, UpAxis::Z, None, None);
+        let z_to_y = |v: [f32; 3]| Vec3::new(v[0], v[2], -v[1]);
+        let pairs = original.geometry.vertices.iter().zip(&turned.geometry.vertices);
+        for (before, after) in pairs {
+            assert!(Vec3::from(after.position).abs_diff_eq(z_to_y(before.position), 1e-6));
+            assert!(Vec3::from(after.normal).abs_diff_eq(z_to_y(before.normal), 1e-6));
+        }
+        assert!((turned.bounds.max.y - 2.0).abs() < 1e-6);
+        assert!(turned.bounds.size().z.abs() < 1e-6);
+
+        let mut unchanged = original.clone();
+        apply(&mut unchanged, UpAxis::Y, None, None);
+        assert_eq!(positions(&unchanged), positions(&original));
+    }
+
+    #[test]
+    fn recentering_moves_the_chosen_center_to_the_origin() {
+        let mut centered = mesh(TRIANGLES);
+        apply(&mut centered, UpAxis::Y, Some(Recenter::BoundsCenter), None);
+        assert!(centered.bounds.center().abs_diff_eq(Vec3::ZERO, 1e-5));
+        assert!((centered.bounds.min.x + 5.05).abs() < 1e-5);
+
+        // The centroid stays next to the large triangle, so the bounding box
+        // reaches much further along +X than -X.
+        let mut centered = mesh(TRIANGLES);
+        apply(&mut centered, UpAxis::Y, Some(Recenter::Centroid), None);
+        assert!(centroid(&centered).abs_diff_eq(Vec3::ZERO, 1e-5));
+        let (area, big) = (4.0, Vec3::new(4.0, 2.0, 0.0) / 3.0);
+        let (tiny_area, tiny) = (0.005, Vec3::new(30.1, 0.1, 0.0) / 3.0);
+        let expected = (big * area + tiny * tiny_area) / (area + tiny_area);
+        assert!(centered.bounds.min.abs_diff_eq(-expected, 1e-5));
+        assert!(centered.bounds.center().x > 3.5);
+    }
+
+    #[test]
+    fn normalizing_scales_to_the_unit_cube_or_sphere() {
+        let mut cube = mesh(TRIANGLES);
+        apply(
+            &mut cube,
+            UpAxis::Y,
+            Some(Recenter::BoundsCenter),
+            Some(Normalize::UnitCube),
+        );
+        let size = cube.bounds.size();
+        assert!((size.max_element() - 1.0).abs() < 1e-6);
+        assert!((size.y - 2.0 / 10.1).abs() < 1e-5);
+        assert!(cube.bounds.min.abs_diff_eq(-size / 2.0, 1e-6));
+
+        let mut sphere = mesh(TRIANGLES);
+        apply(&mut sphere, UpAxis::Y, None, Some(Normalize::UnitSphere));
+        let farthest = positions(&sphere)
+            .iter()
+            .map(|p| p.length())
+            .fold(0.0, f32::max);
+        assert!((farthest - 1.0).abs() < 1e-6);
+        // Only scaled, so (10.1, 0, 0) is the one at a distance of 1
+        assert!((sphere.bounds.max.x - 1.0).abs() < 1e-6);
+        assert!((sphere.bounds.max.y - 2.0 / 10.1).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,695 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use glam::Vec3;
+
+use super::{
+    normals, triangulate, winding, IndexedMesh, NormalVertex, NormalWeighting, ObjError, Winding,
+};
+
+/// Reads ASCII and binary PLY files, as written by most scanning software.
+///
+/// Only the `vertex` and `face` elements are used; every other element is
+/// skipped. Vertices need `x`, `y` and `z`, and may have `nx`, `ny`, `nz`,
+/// `red`, `green`, `blue` and texture coordinates named `u`/`v`, `s`/`t` or
+/// `texture_u`/`texture_v`. Integer colors are scaled to `0.0..=1.0`.
+/// Vertices without a color get the custom color, and smooth normals are
+/// generated for meshes without normals.
+///
+/// Like [`super::Loader`], faces are reversed when `invert_winding_order` is
+/// set, and polygons are split into triangles.
+pub struct PlyLoader {
+    color: [f32; 3],
+    positions: Vec<Vec3>,
+    normals: Option<Vec<[f32; 3]>>,
+    colors: Option<Vec<[f32; 3]>>,
+    uvs: Option<Vec<[f32; 2]>>,
+    // In the winding order of the file
+    triangles: Vec<[usize; 3]>,
+    invert_winding_order: bool,
+    // See `Loader::set_winding_order`
+    clockwise: bool,
+    normal_weighting: NormalWeighting,
+    crease_angle: Option<f32>,
+}
+
+impl PlyLoader {
+    pub fn new(
+        file_name: &str,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<PlyLoader, ObjError> {
+        let input = File::open(file_name).map_err(|source| ObjError::Io {
+            path: file_name.to_string(),
+            source,
+        })?;
+        PlyLoader::from_reader(
+            BufReader::new(input),
+            file_name,
+            custom_color,
+            invert_winding_order,
+        )
+    }
+
+    /// Reads a PLY file from any buffered source. `file_name` is only used
+    /// to report errors.
+    pub fn from_reader(
+        mut input: impl BufRead,
+        file_name: &str,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<PlyLoader, ObjError> {
+        let mut bytes = Vec::new();
+        input
+            .read_to_end(&mut bytes)
+            .map_err(|source| ObjError::Io {
+                path: file_name.to_string(),
+                source,
+            })?;
+        let mut loader = PlyLoader {
+            color: custom_color,
+            positions: Vec::new(),
+            normals: None,
+            colors: None,
+            uvs: None,
+            triangles: Vec::new(),
+            invert_winding_order,
+            clockwise: false,
+            normal_weighting: NormalWeighting::default(),
+            crease_angle: None,
+        };
+        let (line, result) = loader.parse(&bytes);
+        result.map_err(|e| e.at(file_name, line))?;
+        Ok(loader)
+    }
+
+    /// Sets how face normals are weighted when normals are generated for
+    /// files without them.
+    pub fn set_normal_weighting(&mut self, weighting: NormalWeighting) {
+        self.normal_weighting = weighting;
+    }
+
+    /// See [`super::Loader::set_crease_angle`].
+    pub fn set_crease_angle(&mut self, crease_angle: Option<f32>) {
+        self.crease_angle = crease_angle;
+    }
+
+    /// See [`super::Loader::winding_order`]. Vertex normals from the file
+    /// decide if there are any.
+    pub fn winding_order(&self) -> Option<Winding> {
+        let normals: Vec<Option<Vec3>> = self
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let normals = self.normals.as_ref()?;
+                Some(triangle.iter().map(|i| Vec3::from(normals[*i])).sum())
+            })
+            .collect();
+        winding::detect(&self.positions, &self.triangles, &normals)
+    }
+
+    /// See [`super::Loader::set_winding_order`].
+    pub fn set_winding_order(&mut self, winding: Winding) {
+        self.invert_winding_order = winding.needs_invert();
+        self.clockwise = winding == Winding::Clockwise;
+    }
+
+    /// One vertex per triangle corner, like
+    /// [`super::Loader::as_normal_vertices`].
+    pub fn as_normal_vertices(&self) -> Vec<NormalVertex> {
+        let generated = match self.normals {
+            Some(_) => Vec::new(),
+            None => normals::generate(
+                &self.positions,
+                &self.triangles,
+                &vec![1; self.triangles.len()],
+                self.clockwise,
+                self.normal_weighting,
+                self.crease_angle,
+            ),
+        };
+        self.corners()
+            .map(|(triangle, corner)| {
+                let vertex = self.triangles[triangle][corner];
+                NormalVertex {
+                    position: self.positions[vertex].to_array(),
+                    normal: match &self.normals {
+                        Some(normals) => normals[vertex],
+                        None => generated[triangle][corner].to_array(),
+                    },
+                    color: match &self.colors {
+                        Some(colors) => colors[vertex],
+                        None => self.color,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Same as [`PlyLoader::as_normal_vertices`], with identical vertices
+    /// merged.
+    pub fn as_indexed_mesh(&self) -> IndexedMesh {
+        IndexedMesh::from_triangle_list(&self.as_normal_vertices())
+    }
+
+    // The texture coordinates of every corner, in the same order as
+    // `as_normal_vertices`.
+    pub(super) fn corner_uvs(&self) -> Vec<[f32; 2]> {
+        self.corners()
+            .map(|(triangle, corner)| match &self.uvs {
+                Some(uvs) => uvs[self.triangles[triangle][corner]],
+                None => [0.0, 0.0],
+            })
+            .collect()
+    }
+
+    // Every corner as (triangle, corner of the triangle), in output order.
+    fn corners(&self) -> impl Iterator<Item = (usize, usize)> {
+        let order = match self.invert_winding_order {
+            true => [0, 2, 1],
+            false => [0, 1, 2],
+        };
+        (0..self.triangles.len()).flat_map(move |t| order.map(|c| (t, c)))
+    }
+
+    // Returns the line the error happened on together with the result, since
+    // the errors of binary data have no line.
+    fn parse(&mut self, bytes: &[u8]) -> (usize, Result<(), ObjError>) {
+        let (elements, format, body, header_lines) = match parse_header(bytes) {
+            Ok(header) => header,
+            Err((line, e)) => return (line, Err(e)),
+        };
+        let mut body = Body {
+            data: body,
+            format,
+            line: header_lines,
+        };
+        let result = elements
+            .iter()
+            .try_for_each(|element| self.read_element(element, &mut body));
+        let line = match format {
+            Format::Ascii => body.line,
+            Format::Binary { .. } => 0,
+        };
+        (line, result)
+    }
+
+    fn read_element(&mut self, element: &Element, body: &mut Body) -> Result<(), ObjError> {
+        let mut row = Vec::new();
+        let mut starts = Vec::new();
+        match element.name.as_str() {
+            "vertex" => {
+                let scalar = |names: &[&str]| {
+                    element
+                        .properties
+                        .iter()
+                        .position(|p| p.list_count.is_none() && names.contains(&p.name.as_str()))
+                };
+                let all = |x: Option<usize>, y: Option<usize>, z: Option<usize>| Some([x?, y?, z?]);
+                let position = all(scalar(&["x"]), scalar(&["y"]), scalar(&["z"]))
+                    .ok_or_else(|| ObjError::unsupported("PLY vertices without x, y and z"))?;
+                let normal = all(scalar(&["nx"]), scalar(&["ny"]), scalar(&["nz"]));
+                let color = all(
+                    scalar(&["red", "r", "diffuse_red"]),
+                    scalar(&["green", "g", "diffuse_green"]),
+                    scalar(&["blue", "b", "diffuse_blue"]),
+                );
+                let uv = match (
+                    scalar(&["u", "s", "texture_u", "texture_s"]),
+                    scalar(&["v", "t", "texture_v", "texture_t"]),
+                ) {
+                    (Some(u), Some(v)) => Some([u, v]),
+                    _ => None,
+                };
+                let mut normals = Vec::new();
+                let mut colors = Vec::new();
+                let mut uvs = Vec::new();
+                for _ in 0..element.count {
+                    body.read_row(element, &mut row, &mut starts)?;
+                    let value = |p: usize| row[starts[p]] as f32;
+                    self.positions.push(Vec3::from(position.map(value)));
+                    if let Some(normal) = normal {
+                        normals.push(normal.map(value));
+                    }
+                    if let Some(color) = color {
+                        colors.push(
+                            color.map(|p| value(p) / element.properties[p].scalar.color_scale()),
+                        );
+                    }
+                    if let Some(uv) = uv {
+                        uvs.push(uv.map(value));
+                    }
+                }
+                self.normals = normal.map(|_| normals);
+                self.colors = color.map(|_| colors);
+                self.uvs = uv.map(|_| uvs);
+            }
+            "face" => {
+                let indices = element
+                    .properties
+                    .iter()
+                    .position(|p| {
+                        p.list_count.is_some()
+                            && matches!(p.name.as_str(), "vertex_indices" | "vertex_index")
+                    })
+                    .ok_or_else(|| ObjError::unsupported("PLY faces without vertex_indices"))?;
+                for _ in 0..element.count {
+                    body.read_row(element, &mut row, &mut starts)?;
+                    let corners = &row[starts[indices]..starts[indices + 1]];
+                    let mut polygon = Vec::with_capacity(corners.len());
+                    for corner in corners {
+                        let corner = whole_number(*corner)?;
+                        if corner < 0.0 || corner as usize >= self.positions.len() {
+                            return Err(ObjError::index_out_of_range(&corner.to_string()));
+                        }
+                        polygon.push(corner as usize);
+                    }
+                    if polygon.len() < 3 {
+                        return Err(ObjError::malformed(&format!("{:?}", polygon)));
+                    }
+                    let points: Vec<Vec3> = polygon.iter().map(|i| self.positions[*i]).collect();
+                    self.triangles.extend(
+                        triangulate::triangulate(&points)
+                            .into_iter()
+                            .map(|t| t.map(|c| polygon[c])),
+                    );
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_row(element, &mut row, &mut starts)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The value types of PLY properties
+#[derive(Clone, Copy, Debug)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Scalar, ObjError> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(ObjError::unsupported(&format!("PLY type `{}`", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Integer colors use the whole range of their type.
+    fn color_scale(self) -> f32 {
+        match self {
+            Scalar::I8 => i8::MAX as f32,
+            Scalar::U8 => u8::MAX as f32,
+            Scalar::I16 => i16::MAX as f32,
+            Scalar::U16 => u16::MAX as f32,
+            Scalar::I32 => i32::MAX as f32,
+            Scalar::U32 => u32::MAX as f32,
+            Scalar::F32 | Scalar::F64 => 1.0,
+        }
+    }
+
+    // `bytes` holds exactly `size` bytes.
+    fn read(self, bytes: &[u8], big_endian: bool) -> f64 {
+        let mut b = [0; 8];
+        b[..bytes.len()].copy_from_slice(bytes);
+        if big_endian {
+            b[..bytes.len()].reverse();
+        }
+        match self {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    scalar: Scalar,
+    // The type of the item count, for list properties
+    list_count: Option<Scalar>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    Binary { big_endian: bool },
+}
+
+// Returns the elements, the format, the data after the header and the number
+// of header lines, or the line of the error.
+#[allow(clippy::type_complexity)]
+fn parse_header(bytes: &[u8]) -> Result<(Vec<Element>, Format, &[u8], usize), (usize, ObjError)> {
+    let mut elements: Vec<Element> = Vec::new();
+    let mut format = None;
+    let mut rest = bytes;
+    let mut line_number = 0;
+    loop {
+        let end = rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or((line_number, ObjError::malformed("<end of header>")))?;
+        let line = String::from_utf8_lossy(&rest[..end]);
+        let line = line.trim();
+        rest = &rest[end + 1..];
+        line_number += 1;
+        let fail = |e: ObjError| (line_number, e);
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            _ if line_number == 1 && line != "ply" => {
+                return Err(fail(ObjError::unsupported("file without a PLY header")));
+            }
+            Some("format") => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::Binary { big_endian: false },
+                    Some("binary_big_endian") => Format::Binary { big_endian: true },
+                    _ => return Err(fail(ObjError::malformed(line))),
+                });
+            }
+            Some("element") => {
+                let (name, count) = match (tokens.next(), tokens.next()) {
+                    (Some(name), Some(count)) => (name, count),
+                    _ => return Err(fail(ObjError::malformed(line))),
+                };
+                elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| fail(ObjError::invalid_number(count)))?,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let tokens: Vec<&str> = tokens.collect();
+                let property = match tokens.as_slice() {
+                    ["list", count, scalar, name] => Property {
+                        name: name.to_string(),
+                        scalar: Scalar::parse(scalar).map_err(fail)?,
+                        list_count: Some(Scalar::parse(count).map_err(fail)?),
+                    },
+                    [scalar, name] => Property {
+                        name: name.to_string(),
+                        scalar: Scalar::parse(scalar).map_err(fail)?,
+                        list_count: None,
+                    },
+                    _ => return Err(fail(ObjError::malformed(line))),
+                };
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => return Err(fail(ObjError::malformed(line))),
+                }
+            }
+            Some("end_header") => break,
+            _ => {}
+        }
+    }
+    let format = format.ok_or((line_number, ObjError::malformed("<missing format>")))?;
+    Ok((elements, format, rest, line_number))
+}
+
+// The data after the header
+struct Body<'a> {
+    data: &'a [u8],
+    format: Format,
+    // The last line read, for ASCII data
+    line: usize,
+}
+
+impl Body<'_> {
+    // Reads one row of `element` into `row`. Property `i` is found at
+    // `row[starts[i]..starts[i + 1]]`.
+    fn read_row(
+        &mut self,
+        element: &Element,
+        row: &mut Vec<f64>,
+        starts: &mut Vec<usize>,
+    ) -> Result<(), ObjError> {
+        row.clear();
+        starts.clear();
+        match self.format {
+            Format::Ascii => {
+                let line = loop {
+                    let end = self.data.iter().position(|b| *b == b'\n');
+                    let (line, rest) = match end {
+                        Some(end) => (&self.data[..end], &self.data[end + 1..]),
+                        None if self.data.is_empty() => {
+                            return Err(ObjError::malformed("<end of file>"))
+                        }
+                        None => (self.data, &self.data[self.data.len()..]),
+                    };
+                    self.data = rest;
+                    self.line += 1;
+                    let line = String::from_utf8_lossy(line);
+                    if !line.trim().is_empty() {
+                        break line.into_owned();
+                    }
+                };
+                let mut tokens = line.split_whitespace();
+                let mut next = || {
+                    let token = tokens
+                        .next()
+                        .ok_or_else(|| ObjError::malformed(line.trim()))?;
+                    token
+                        .parse::<f64>()
+                        .map_err(|_| ObjError::invalid_number(token))
+                };
+                for property in &element.properties {
+                    starts.push(row.len());
+                    let count = match property.list_count {
+                        Some(_) => list_count(next()?)?,
+                        None => 1,
+                    };
+                    for _ in 0..count {
+                        row.push(next()?);
+                    }
+                }
+                if tokens.next().is_some() {
+                    return Err(ObjError::malformed(line.trim()));
+                }
+            }
+            Format::Binary { big_endian } => {
+                let mut next = |scalar: Scalar| {
+                    if self.data.len() < scalar.size() {
+                        return Err(ObjError::malformed("<end of file>"));
+                    }
+                    let (bytes, rest) = self.data.split_at(scalar.size());
+                    self.data = rest;
+                    Ok(scalar.read(bytes, big_endian))
+                };
+                for property in &element.properties {
+                    starts.push(row.len());
+                    let count = match property.list_count {
+                        Some(count) => list_count(next(count)?)?,
+                        None => 1,
+                    };
+                    for _ in 0..count {
+                        row.push(next(property.scalar)?);
+                    }
+                }
+            }
+        }
+        starts.push(row.len());
+        Ok(())
+    }
+}
+
+// Every value is read as a number, including the ones that count or index
+// something, which must be whole.
+fn whole_number(value: f64) -> Result<f64, ObjError> {
+    match value.is_finite() && value.fract() == 0.0 {
+        true => Ok(value),
+        false => Err(ObjError::invalid_number(&value.to_string())),
+    }
+}
+
+fn list_count(value: f64) -> Result<usize, ObjError> {
+    match whole_number(value)? {
+        count if count < 0.0 => Err(ObjError::invalid_number(&count.to_string())),
+        count => Ok(count as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+    // A unit square in the XY plane with 8-bit colors, as one quad.
+    fn header(format: &str) -> String {
+        format!(
+            "ply\nformat {format} 1.0\ncomment made by hand\n\
+             element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\n\
+             element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+        )
+    }
+
+    const POSITIONS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    const COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [51, 102, 255]];
+
+    fn ascii() -> Vec<u8> {
+        let mut ply = header("ascii");
+        for (p, c) in POSITIONS.iter().zip(COLORS) {
+            ply += &format!("{} {} {} {} {} {}\n", p[0], p[1], p[2], c[0], c[1], c[2]);
+        }
+        ply += "4 0 1 2 3\n";
+        ply.into_bytes()
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = match big_endian {
+            true => "binary_big_endian",
+            false => "binary_little_endian",
+        };
+        let mut ply = header(format).into_bytes();
+        let float = |x: f32| {
+            if big_endian {
+                x.to_be_bytes()
+            } else {
+                x.to_le_bytes()
+            }
+        };
+        let int = |x: i32| {
+            if big_endian {
+                x.to_be_bytes()
+            } else {
+                x.to_le_bytes()
+            }
+        };
+        for (p, c) in POSITIONS.iter().zip(COLORS) {
+            p.iter().for_each(|x| ply.extend(float(*x)));
+            ply.extend(c);
+        }
+        ply.push(4);
+        (0..4).for_each(|i| ply.extend(int(i)));
+        ply
+    }
+
+    fn load(ply: &[u8]) -> Result<PlyLoader, ObjError> {
+        PlyLoader::from_reader(ply, "test.ply", COLOR, false)
+    }
+
+    #[test]
+    fn ascii_quads_are_triangulated() {
+        let loader = load(&ascii()).unwrap();
+        let vertices = loader.as_normal_vertices();
+        assert_eq!(vertices.len(), 6);
+        for vertex in &vertices {
+            let corner = POSITIONS
+                .iter()
+                .position(|p| *p == vertex.position)
+                .unwrap();
+            assert_eq!(vertex.color, COLORS[corner].map(|c| c as f32 / 255.0));
+            // Generated normals of a counter-clockwise file
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        }
+        assert_eq!(loader.as_indexed_mesh().vertices.len(), 4);
+        assert_eq!(loader.winding_order(), None);
+    }
+
+    #[test]
+    fn u8_colors_are_scaled() {
+        let loader = load(&ascii()).unwrap();
+        let colors = loader.colors.as_ref().unwrap();
+        assert_eq!(colors[0], [1.0, 0.0, 0.0]);
+        assert_eq!(colors[3], [0.2, 0.4, 1.0]);
+    }
+
+    #[test]
+    fn binary_files_read_like_ascii_ones() {
+        let ascii = load(&ascii()).unwrap().as_normal_vertices();
+        for big_endian in [false, true] {
+            let binary = load(&binary(big_endian)).unwrap().as_normal_vertices();
+            assert_eq!(
+                bytemuck::cast_slice::<_, u8>(&binary),
+                bytemuck::cast_slice::<_, u8>(&ascii),
+            );
+        }
+    }
+
+    #[test]
+    fn indices_must_be_whole_and_in_range() {
+        let with_face = |face: &str| {
+            let mut ply = String::from_utf8(ascii()).unwrap();
+            ply.truncate(ply.trim_end().rfind('\n').unwrap() + 1);
+            ply + face
+        };
+        for face in [
+            "4 0 1 2 1.5\n",
+            "4 0 1 2 nan\n",
+            "4 0 1 2 inf\n",
+            "1.5 0 1 2\n",
+        ] {
+            let error = load(with_face(face).as_bytes()).err().unwrap();
+            assert!(
+                matches!(error, ObjError::InvalidNumber { line: 18, .. }),
+                "{face}: {error}"
+            );
+        }
+        let error = load(with_face("4 0 1 2 4\n").as_bytes()).err().unwrap();
+        assert!(matches!(error, ObjError::IndexOutOfRange { .. }), "{error}");
+
+        // Binary errors have no line.
+        let mut ply = header("binary_little_endian")
+            .replace("uchar int vertex_indices", "uchar float vertex_indices")
+            .into_bytes();
+        for (p, c) in POSITIONS.iter().zip(COLORS) {
+            p.iter().for_each(|x| ply.extend(x.to_le_bytes()));
+            ply.extend(c);
+        }
+        ply.push(4);
+        [0.0f32, 1.0, 2.0, 2.5]
+            .iter()
+            .for_each(|x| ply.extend(x.to_le_bytes()));
+        let error = load(&ply).err().unwrap();
+        assert!(
+            matches!(error, ObjError::InvalidNumber { line: 0, .. }),
+            "{error}"
+        );
+    }
+}
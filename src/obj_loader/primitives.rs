@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::Vec3;
+
+use super::{Mesh, NormalVertex};
+
+/// A mesh generated in code, for gizmos, placeholders and tests that
+/// shouldn't depend on files in the working directory.
+///
+/// Every shape fits the cube from -1 to 1, centered on the origin with +Y up,
+/// except for the torus and capsule which are as long as their parameters
+/// make them. Resolutions below the minimum a shape needs are raised to it.
+/// Texture coordinates run from 0 to 1 across each surface, with `v`
+/// pointing up as in obj files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Primitive {
+    /// A sphere of radius 1 made of `segments` slices around the Y axis and
+    /// `rings` stacks from pole to pole.
+    UvSphere { segments: u32, rings: u32 },
+    /// A sphere of radius 1 made by splitting every triangle of an
+    /// icosahedron into four `subdivisions` times, which spreads the
+    /// triangles more evenly than [`Primitive::UvSphere`].
+    IcoSphere { subdivisions: u32 },
+    /// A cube with sides of length 2, with every face split into
+    /// `divisions` by `divisions` squares.
+    Cube { divisions: u32 },
+    /// A 2 by 2 square in the XZ plane facing +Y, split into `columns` along
+    /// X and `rows` along Z. A plane is a grid of 1 by 1.
+    Grid { columns: u32, rows: u32 },
+    /// A capped cylinder of radius 1 and height 2 around the Y axis.
+    Cylinder { segments: u32 },
+    /// A cone of radius 1 and height 2 with its base at -1 and its tip at
+    /// +1 on the Y axis.
+    Cone { segments: u32 },
+    /// A ring of radius 1 around the Y axis, made of a tube of
+    /// `tube_radius` with `sides` sides, split into `segments` around the
+    /// ring.
+    Torus {
+        segments: u32,
+        sides: u32,
+        tube_radius: f32,
+    },
+    /// A cylinder of radius 1 capped by two half spheres, with `height`
+    /// between the centers of the half spheres. `rings` is the number of
+    /// stacks of each half sphere.
+    Capsule {
+        segments: u32,
+        rings: u32,
+        height: f32,
+    },
+}
+
+// A triangle corner of a generated shape
+#[derive(Clone, Copy)]
+struct Corner {
+    position: Vec3,
+    normal: Vec3,
+    uv: [f32; 2],
+}
+
+impl Primitive {
+    /// Generates the shape with a single sub-mesh and no materials. Like
+    /// [`super::ModelBuilder`], faces are reversed when `invert_winding_order`
+    /// is set, so both are drawn by the same pipelines.
+    pub fn mesh(&self, color: [f32; 3], invert_winding_order: bool) -> Mesh {
+        let mut corners = self.corners();
+        if invert_winding_order {
+            corners
+                .chunks_mut(3)
+                .for_each(|triangle| triangle.swap(1, 2));
+        }
+        let vertices: Vec<NormalVertex> = corners
+            .iter()
+            .map(|c| NormalVertex {
+                position: c.position.to_array(),
+                normal: c.normal.to_array(),
+                color,
+            })
+            .collect();
+        let uvs: Vec<[f32; 2]> = corners.iter().map(|c| c.uv).collect();
+        super::single_mesh(self.name(), &vertices, &uvs)
+    }
+
+    // Used as the object name of the sub-mesh
+    fn name(&self) -> &'static str {
+        match self {
+            Primitive::UvSphere { .. } => "uv_sphere",
+            Primitive::IcoSphere { .. } => "ico_sphere",
+            Primitive::Cube { .. } => "cube",
+            Primitive::Grid { .. } => "grid",
+            Primitive::Cylinder { .. } => "cylinder",
+            Primitive::Cone { .. } => "cone",
+            Primitive::Torus { .. } => "torus",
+            Primitive::Capsule { .. } => "capsule",
+        }
+    }
+
+    // The triangles of the shape, counter-clockwise seen from outside.
+    fn corners(&self) -> Vec<Corner> {
+        let mut list = Vec::new();
+        match *self {
+            Primitive::UvSphere { segments, rings } => {
+                let (segments, rings) = (segments.max(3), rings.max(2));
+                patch(&mut list, segments, rings, |i, j| {
+                    let latitude = j as f32 / rings as f32 * PI - FRAC_PI_2;
+                    let normal = around_y(i, segments, latitude);
+                    Corner {
+                        position: normal,
+                        normal,
+                        uv: [i as f32 / segments as f32, j as f32 / rings as f32],
+                    }
+                });
+            }
+            Primitive::IcoSphere { subdivisions } => ico_sphere(&mut list, subdivisions),
+            Primitive::Cube { divisions } => {
+                let divisions = divisions.max(1);
+                // The normal of each face and the directions of u and v on
+                // it, with u x v = normal
+                let faces = [
+                    (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+                    (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+                    (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+                    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+                    (Vec3::Z, Vec3::X, Vec3::Y),
+                    (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+                ];
+                // Computed from the integer steps so that the edges shared by
+                // two faces get bit-for-bit the same positions
+                let coordinate =
+                    |k: u32| (2 * k as i32 - divisions as i32) as f32 / divisions as f32;
+                for (normal, u, v) in faces {
+                    patch(&mut list, divisions, divisions, |i, j| Corner {
+                        position: normal + u * coordinate(i) + v * coordinate(j),
+                        normal,
+                        uv: [i, j].map(|x| x as f32 / divisions as f32),
+                    });
+                }
+            }
+            Primitive::Grid { columns, rows } => {
+                let (columns, rows) = (columns.max(1), rows.max(1));
+                patch(&mut list, columns, rows, |i, j| {
+                    let [s, t] = [i as f32 / columns as f32, j as f32 / rows as f32];
+                    Corner {
+                        position: Vec3::new(s * 2.0 - 1.0, 0.0, 1.0 - t * 2.0),
+                        normal: Vec3::Y,
+                        uv: [s, t],
+                    }
+                });
+            }
+            Primitive::Cylinder { segments } => {
+                let segments = segments.max(3);
+                patch(&mut list, segments, 1, |i, j| {
+                    let normal = around_y(i, segments, 0.0);
+                    Corner {
+                        position: normal + Vec3::Y * (j as f32 * 2.0 - 1.0),
+                        normal,
+                        uv: [i as f32 / segments as f32, j as f32],
+                    }
+                });
+                cap(&mut list, segments, 1.0);
+                cap(&mut list, segments, -1.0);
+            }
+            Primitive::Cone { segments } => {
+                let segments = segments.max(3);
+                patch(&mut list, segments, 1, |i, j| {
+                    let radial = around_y(i, segments, 0.0);
+                    let t = j as f32;
+                    Corner {
+                        position: radial * (1.0 - t) + Vec3::Y * (t * 2.0 - 1.0),
+                        // The slope of a cone of radius 1 and height 2
+                        normal: (radial * 2.0 + Vec3::Y).normalize(),
+                        uv: [i as f32 / segments as f32, t],
+                    }
+                });
+                cap(&mut list, segments, -1.0);
+            }
+            Primitive::Torus {
+                segments,
+                sides,
+                tube_radius,
+            } => {
+                let (segments, sides) = (segments.max(3), sides.max(3));
+                patch(&mut list, segments, sides, |i, j| {
+                    let radial = around_y(i, segments, 0.0);
+                    let angle = (j % sides) as f32 / sides as f32 * TAU;
+                    let normal = radial * angle.cos() + Vec3::Y * angle.sin();
+                    Corner {
+                        position: radial + normal * tube_radius,
+                        normal,
+                        uv: [i as f32 / segments as f32, j as f32 / sides as f32],
+                    }
+                });
+            }
+            Primitive::Capsule {
+                segments,
+                rings,
+                height,
+            } => {
+                let (segments, rings) = (segments.max(3), rings.max(1));
+                let half = height * 0.5;
+                // Row `rings` ends the lower half sphere and row `rings + 1`
+                // starts the upper one, the band between them is the
+                // cylinder.
+                patch(&mut list, segments, rings * 2 + 1, |i, j| {
+                    let (latitude, center) = match j.checked_sub(rings + 1) {
+                        None => (j as f32 / rings as f32 - 1.0, -half),
+                        Some(k) => (k as f32 / rings as f32, half),
+                    };
+                    let normal = around_y(i, segments, latitude * FRAC_PI_2);
+                    let position = normal + Vec3::Y * center;
+                    Corner {
+                        position,
+                        normal,
+                        uv: [
+                            i as f32 / segments as f32,
+                            (position.y + half + 1.0) / (height + 2.0),
+                        ],
+                    }
+                });
+            }
+        }
+        list
+    }
+}
+
+// The unit vector at `latitude` radians and the longitude of column `i` of
+// `segments`. The last column and the poles are snapped to exactly the same
+// positions as the first column and the other pole vertices.
+fn around_y(i: u32, segments: u32, latitude: f32) -> Vec3 {
+    let longitude = (i % segments) as f32 / segments as f32 * TAU;
+    let (y, ring) = latitude.sin_cos();
+    if latitude.abs() >= FRAC_PI_2 {
+        return Vec3::new(0.0, latitude.signum(), 0.0);
+    }
+    Vec3::new(longitude.sin() * ring, y, longitude.cos() * ring)
+}
+
+// Adds a grid of `columns` by `rows` quads between the corners `corner(i, j)`
+// for i in 0..=columns and j in 0..=rows. The quads are counter-clockwise
+// when going along i and then j turns left when seen from outside.
+// Triangles that collapse, e.g. at the poles of a sphere, are left out.
+fn patch(list: &mut Vec<Corner>, columns: u32, rows: u32, corner: impl Fn(u32, u32) -> Corner) {
+    for j in 0..rows {
+        for i in 0..columns {
+            let [a, b, c, d] =
+                [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)].map(|(i, j)| corner(i, j));
+            for triangle in [[a, b, c], [a, c, d]] {
+                let [p, q, r] = triangle.map(|c| c.position);
+                if p != q && q != r && r != p {
+                    list.extend(triangle);
+                }
+            }
+        }
+    }
+}
+
+// A disk of radius 1 closing a cylinder at height `y`, facing away from the
+// center.
+fn cap(list: &mut Vec<Corner>, segments: u32, y: f32) {
+    let normal = Vec3::Y * y.signum();
+    patch(list, segments, 1, |i, j| {
+        // Going from the rim to the center turns left on the top cap and
+        // right on the bottom one.
+        let radius = if y > 0.0 { 1.0 - j as f32 } else { j as f32 };
+        let position = around_y(i, segments, 0.0) * radius + Vec3::Y * y;
+        Corner {
+            position,
+            normal,
+            uv: [position.x * 0.5 + 0.5, position.z * 0.5 + 0.5],
+        }
+    });
+}
+
+fn ico_sphere(list: &mut Vec<Corner>, subdivisions: u32) {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut positions: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| Vec3::new(x, y, z).normalize())
+    .collect();
+    let mut triangles: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Neighbouring triangles share the vertex in the middle of their
+        // common edge.
+        let mut middles: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut middle = |a: usize, b: usize| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push((positions[a] + positions[b]).normalize());
+                positions.len() - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    for triangle in triangles {
+        let mut corners = triangle.map(|i| {
+            let p = positions[i];
+            Corner {
+                position: p,
+                normal: p,
+                uv: [0.5 + p.x.atan2(p.z) / TAU, 0.5 + p.y.asin() / PI],
+            }
+        });
+        // Triangles across the seam at the back get their texture
+        // coordinates continued past 1 instead of wrapping back to 0.
+        let [a, b, c] = corners.map(|c| c.uv[0]);
+        if a.max(b).max(c) - a.min(b).min(c) > 0.5 {
+            for corner in corners.iter_mut().filter(|c| c.uv[0] < 0.5) {
+                corner.uv[0] += 1.0;
+            }
+        }
+        list.extend(corners);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: [f32; 3] = [1.0; 3];
+
+    const SHAPES: [Primitive; 8] = [
+        Primitive::UvSphere {
+            segments: 16,
+            rings: 8,
+        },
+        Primitive::IcoSphere { subdivisions: 2 },
+        Primitive::Cube { divisions: 2 },
+        Primitive::Grid {
+            columns: 3,
+            rows: 2,
+        },
+        Primitive::Cylinder { segments: 12 },
+        Primitive::Cone { segments: 12 },
+        Primitive::Torus {
+            segments: 16,
+            sides: 8,
+            tube_radius: 0.25,
+        },
+        Primitive::Capsule {
+            segments: 12,
+            rings: 4,
+            height: 1.0,
+        },
+    ];
+
+    // The point of the shape that `position` faces away from
+    fn inside(shape: &Primitive, position: Vec3) -> Vec3 {
+        match shape {
+            // Its normals all face +Y, checked against the winding below
+            Primitive::Grid { .. } => position - Vec3::Y,
+            Primitive::Torus { .. } => (position * Vec3::new(1.0, 0.0, 1.0)).normalize(),
+            _ => Vec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn triangles_face_outwards_with_unit_normals() {
+        for shape in SHAPES {
+            let mesh = shape.mesh(COLOR, false);
+            let geometry = &mesh.geometry;
+            let indices: Vec<usize> = geometry.indices.iter().collect();
+            assert!(!indices.is_empty(), "{shape:?}");
+            for triangle in indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| geometry.vertices[triangle[k]]);
+                let [p, q, r] = [a, b, c].map(|v| Vec3::from(v.position));
+                // Counter-clockwise seen from the side the normals point to
+                let face = (q - p).cross(r - p);
+                for vertex in [a, b, c] {
+                    let normal = Vec3::from(vertex.normal);
+                    let position = Vec3::from(vertex.position);
+                    assert!((normal.length() - 1.0).abs() < 1e-5, "{shape:?}");
+                    assert!(face.dot(normal) > 0.0, "{shape:?} at {position}");
+                    assert!(
+                        normal.dot(position - inside(&shape, position)) > 0.0,
+                        "{shape:?} at {position}"
+                    );
+                }
+            }
+
+            // Inverting the winding only swaps the corners
+            let inverted = shape.mesh(COLOR, true);
+            let triangles = geometry.to_triangle_list();
+            let swapped = inverted.geometry.to_triangle_list();
+            assert_eq!(triangles.len(), swapped.len());
+            for (t, s) in triangles.chunks(3).zip(swapped.chunks(3)) {
+                let reversed = [t[0], t[2], t[1]].map(|v| v.position);
+                assert_eq!(reversed, [s[0], s[1], s[2]].map(|v| v.position));
+            }
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_stay_between_0_and_1() {
+        for shape in SHAPES {
+            let mesh = shape.mesh(COLOR, false);
+            assert_eq!(mesh.uvs.len(), mesh.geometry.vertices.len());
+            for &[u, v] in &mesh.uvs {
+                // The ico sphere continues `u` past 1 across its seam, for
+                // samplers that repeat.
+                let max_u = match shape {
+                    Primitive::IcoSphere { .. } => 1.5,
+                    _ => 1.0,
+                };
+                assert!((0.0..=max_u).contains(&u), "{shape:?}: u = {u}");
+                assert!((0.0..=1.0).contains(&v), "{shape:?}: v = {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_have_their_documented_extents() {
+        for shape in SHAPES {
+            let bounds = shape.mesh(COLOR, false).bounds;
+            let half = match shape {
+                Primitive::Grid { .. } => Vec3::new(1.0, 0.0, 1.0),
+                Primitive::Torus { tube_radius, .. } => {
+                    Vec3::new(1.0 + tube_radius, tube_radius, 1.0 + tube_radius)
+                }
+                Primitive::Capsule { height, .. } => Vec3::new(1.0, height / 2.0 + 1.0, 1.0),
+                _ => Vec3::ONE,
+            };
+            assert!(bounds.max.abs_diff_eq(half, 1e-5), "{shape:?}: {bounds:?}");
+            assert!(bounds.min.abs_diff_eq(-half, 1e-5), "{shape:?}: {bounds:?}");
+        }
+    }
+
+    #[test]
+    fn low_resolutions_are_raised_to_the_minimum() {
+        let pairs = [
+            (
+                Primitive::UvSphere {
+                    segments: 0,
+                    rings: 1,
+                },
+                Primitive::UvSphere {
+                    segments: 3,
+                    rings: 2,
+                },
+            ),
+            (Primitive::Cube { divisions: 0 }, Primitive::Cube { divisions: 1 }),
+            (
+                Primitive::Grid {
+                    columns: 0,
+                    rows: 0,
+                },
+                Primitive::Grid {
+                    columns: 1,
+                    rows: 1,
+                },
+            ),
+            (
+                Primitive::Cylinder { segments: 2 },
+                Primitive::Cylinder { segments: 3 },
+            ),
+            (Primitive::Cone { segments: 0 }, Primitive::Cone { segments: 3 }),
+            (
+                Primitive::Torus {
+                    segments: 1,
+                    sides: 0,
+                    tube_radius: 0.25,
+                },
+                Primitive::Torus {
+                    segments: 3,
+                    sides: 3,
+                    tube_radius: 0.25,
+                },
+            ),
+            (
+                Primitive::Capsule {
+                    segments: 0,
+                    rings: 0,
+                    height: 1.0,
+                },
+                Primitive::Capsule {
+                    segments: 3,
+                    rings: 1,
+                    height: 1.0,
+                },
+            ),
+        ];
+        for (low, minimum) in pairs {
+            let low = low.mesh(COLOR, false).geometry.to_triangle_list();
+            let minimum = minimum.mesh(COLOR, false).geometry.to_triangle_list();
+            assert!(!minimum.is_empty());
+            assert_eq!(
+                bytemuck::cast_slice::<_, u8>(&low),
+                bytemuck::cast_slice::<_, u8>(&minimum)
+            );
+        }
+    }
+}
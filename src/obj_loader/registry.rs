@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use glam::Vec3;
+
+use super::{Mesh, Model, ModelBuilder, ObjError, SourceKey};
+
+/// Loads every mesh once and hands out shared handles to it.
+///
+/// Meshes are keyed by their source and the builder options that change
+/// their geometry, so asking for the same file with a different scale
+/// factor returns the same mesh, while a different color does not. The
+/// source is the file name for files, the parameters for primitives and
+/// the whole text for [`Model::from_obj_str`], which is kept in the
+/// registry until [`MeshRegistry::purge`] drops the mesh. Builders with a
+/// custom [`super::Resolver`] are loaded every time, as their output can't
+/// be told apart by file name.
+#[derive(Default)]
+pub struct MeshRegistry {
+    meshes: HashMap<(SourceKey, Vec<u8>), Arc<Mesh>>,
+}
+
+impl MeshRegistry {
+    pub fn new() -> MeshRegistry {
+        MeshRegistry::default()
+    }
+
+    /// The mesh `builder` would load, parsed only on first use.
+    pub fn mesh(&mut self, builder: ModelBuilder) -> Result<Arc<Mesh>, ObjError> {
+        let key = match builder.registry_key() {
+            Some(key) => key,
+            None => return Ok(Arc::new(builder.build_mesh()?)),
+        };
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Ok(mesh.clone());
+        }
+        let mesh = Arc::new(builder.build_mesh()?);
+        self.meshes.insert(key, mesh.clone());
+        Ok(mesh)
+    }
+
+    /// Like [`ModelBuilder::build`], with the mesh shared through
+    /// [`MeshRegistry::mesh`].
+    pub fn model(&mut self, builder: ModelBuilder) -> Result<Model, ObjError> {
+        let scale_factor = builder.scale_factor;
+        let cull_mode = builder.cull_mode;
+        let mut model = Model::from_mesh(self.mesh(builder)?);
+        model.transform.scale = Vec3::splat(scale_factor);
+        model.cull_mode = cull_mode;
+        Ok(model)
+    }
+
+    /// Forgets the meshes no longer used outside of the registry, returning
+    /// how many were dropped.
+    pub fn purge(&mut self) -> usize {
+        let before = self.meshes.len();
+        self.meshes.retain(|_, mesh| Arc::strong_count(mesh) > 1);
+        before - self.meshes.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj_loader::Primitive;
+
+    const OBJ: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    #[test]
+    fn the_same_source_and_options_share_a_mesh() {
+        let mut registry = MeshRegistry::new();
+        let first = registry.mesh(Model::from_obj_str(OBJ)).unwrap();
+        let second = registry
+            .model(Model::from_obj_str(OBJ).uniform_scale_factor(2.0))
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, second.shared_mesh()));
+        assert_eq!(second.scale(), Vec3::splat(2.0));
+        assert_eq!(registry.len(), 1);
+
+        let cube = Primitive::Cube { divisions: 1 };
+        let a = registry.mesh(Model::primitive(cube)).unwrap();
+        let b = registry.mesh(Model::primitive(cube)).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        let finer = Primitive::Cube { divisions: 2 };
+        let c = registry.mesh(Model::primitive(finer)).unwrap();
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn options_that_change_the_geometry_load_another_mesh() {
+        let mut registry = MeshRegistry::new();
+        let grey = registry.mesh(Model::from_obj_str(OBJ)).unwrap();
+        let red = registry
+            .mesh(Model::from_obj_str(OBJ).color([1.0, 0.0, 0.0]))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&grey, &red));
+        assert_eq!(red.geometry().vertices[0].color, [1.0, 0.0, 0.0]);
+        let other = registry
+            .mesh(Model::from_obj_str("v 0 0 0\nv 0 1 0\nv 1 0 0\nf 1 2 3\n"))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&grey, &other));
+        assert_eq!(registry.len(), 3);
+
+        // Resolvers bypass the registry.
+        let resolver = |_: &str| -> std::io::Result<Box<dyn std::io::BufRead>> {
+            Err(std::io::ErrorKind::NotFound.into())
+        };
+        let resolved = registry
+            .mesh(Model::from_obj_str(OBJ).resolver(resolver))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&grey, &resolved));
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn purge_drops_meshes_only_the_registry_holds() {
+        let mut registry = MeshRegistry::new();
+        let kept = registry.model(Model::from_obj_str(OBJ)).unwrap();
+        registry
+            .model(Model::from_obj_str(OBJ).color([0.0, 0.0, 1.0]))
+            .unwrap();
+        assert_eq!(registry.purge(), 1);
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.purge(), 0);
+
+        drop(kept);
+        assert_eq!(registry.purge(), 1);
+        assert!(registry.is_empty());
+    }
+}
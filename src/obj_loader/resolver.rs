@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Finds and opens the mtl libraries an obj file refers to.
+///
+/// Closures of the form `|path| -> io::Result<Box<dyn BufRead>>` implement
+/// this trait and keep every path as written, which is handy for libraries
+/// embedded with `include_str!` or read from an archive.
+pub trait Resolver {
+    /// Turns `path`, as written in the file named `referenced_by`, into the
+    /// name used to open it. Keeps the path as written by default.
+    fn resolve(&self, referenced_by: &str, path: &str) -> String {
+        let _ = referenced_by;
+        path.to_string()
+    }
+
+    /// Opens a file name returned by [`Resolver::resolve`].
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead>>;
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> io::Result<Box<dyn BufRead>>,
+{
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead>> {
+        self(path)
+    }
+}
+
+/// Reads referenced files from the file system, relative to the directory of
+/// the file that references them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileResolver;
+
+impl Resolver for FileResolver {
+    fn resolve(&self, referenced_by: &str, path: &str) -> String {
+        match Path::new(referenced_by).parent() {
+            Some(dir) => dir.join(path).to_string_lossy().into_owned(),
+            None => path.to_string(),
+        }
+    }
+
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
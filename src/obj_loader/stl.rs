@@ -0,0 +1,297 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use glam::Vec3;
+
+use super::{winding, IndexedMesh, NormalVertex, ObjError, Winding};
+
+/// Reads ASCII and binary STL files, as exported by CAD software.
+///
+/// STL has no shared vertices, so every facet is drawn flat with its own
+/// normal. Facets whose normal is missing, zero or not a number get the
+/// normal of their corners instead. Every vertex gets the custom color.
+///
+/// Like [`super::Loader`], faces are reversed when `invert_winding_order` is
+/// set.
+pub struct StlLoader {
+    color: [f32; 3],
+    name: String,
+    facets: Vec<Facet>,
+    invert_winding_order: bool,
+    // See `Loader::set_winding_order`
+    clockwise: bool,
+}
+
+struct Facet {
+    // `None` if the file has no usable normal
+    normal: Option<Vec3>,
+    // In the winding order of the file
+    corners: [[f32; 3]; 3],
+}
+
+impl StlLoader {
+    pub fn new(
+        file_name: &str,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<StlLoader, ObjError> {
+        let input = File::open(file_name).map_err(|source| ObjError::Io {
+            path: file_name.to_string(),
+            source,
+        })?;
+        StlLoader::from_reader(
+            BufReader::new(input),
+            file_name,
+            custom_color,
+            invert_winding_order,
+        )
+    }
+
+    /// Reads an STL file from any buffered source. `file_name` is only used
+    /// to report errors.
+    pub fn from_reader(
+        mut input: impl BufRead,
+        file_name: &str,
+        custom_color: [f32; 3],
+        invert_winding_order: bool,
+    ) -> Result<StlLoader, ObjError> {
+        let mut bytes = Vec::new();
+        input
+            .read_to_end(&mut bytes)
+            .map_err(|source| ObjError::Io {
+                path: file_name.to_string(),
+                source,
+            })?;
+        // Binary files may start with `solid` as well, so the size, which
+        // is fixed by the facet count, decides.
+        let binary_count = bytes
+            .get(80..84)
+            .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize);
+        let (name, facets) = match binary_count {
+            Some(count) if bytes.len() as u64 == 84 + 50 * count as u64 => {
+                (String::new(), read_binary(&bytes[84..]))
+            }
+            _ if bytes.trim_ascii_start().starts_with(b"solid") => {
+                read_ascii(&String::from_utf8_lossy(&bytes))
+                    .map_err(|(line, e)| e.at(file_name, line))?
+            }
+            _ => {
+                let e = ObjError::unsupported("file that is neither ASCII nor binary STL");
+                return Err(e.at(file_name, 0));
+            }
+        };
+        Ok(StlLoader {
+            color: custom_color,
+            name,
+            facets,
+            invert_winding_order,
+            clockwise: false,
+        })
+    }
+
+    /// The name after `solid` of an ASCII file, empty for binary files.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// See [`super::Loader::winding_order`]. Facet normals from the file
+    /// decide if there are any.
+    pub fn winding_order(&self) -> Option<Winding> {
+        let positions: Vec<Vec3> = self
+            .facets
+            .iter()
+            .flat_map(|facet| facet.corners.map(Vec3::from))
+            .collect();
+        let triangles: Vec<[usize; 3]> = (0..self.facets.len())
+            .map(|f| [f * 3, f * 3 + 1, f * 3 + 2])
+            .collect();
+        let normals: Vec<Option<Vec3>> = self.facets.iter().map(|facet| facet.normal).collect();
+        winding::detect(&positions, &triangles, &normals)
+    }
+
+    /// See [`super::Loader::set_winding_order`].
+    pub fn set_winding_order(&mut self, winding: Winding) {
+        self.invert_winding_order = winding.needs_invert();
+        self.clockwise = winding == Winding::Clockwise;
+    }
+
+    /// One vertex per triangle corner, like
+    /// [`super::Loader::as_normal_vertices`].
+    pub fn as_normal_vertices(&self) -> Vec<NormalVertex> {
+        let order = match self.invert_winding_order {
+            true => [0, 2, 1],
+            false => [0, 1, 2],
+        };
+        self.facets
+            .iter()
+            .flat_map(|facet| {
+                let normal = facet.normal.unwrap_or_else(|| {
+                    let [a, b, c] = facet.corners.map(Vec3::from);
+                    let normal = (b - a).cross(c - a).normalize_or_zero();
+                    match self.clockwise {
+                        true => -normal,
+                        false => normal,
+                    }
+                });
+                order.map(|corner| NormalVertex {
+                    position: facet.corners[corner],
+                    normal: normal.to_array(),
+                    color: self.color,
+                })
+            })
+            .collect()
+    }
+
+    /// Same as [`StlLoader::as_normal_vertices`], with identical vertices
+    /// merged.
+    pub fn as_indexed_mesh(&self) -> IndexedMesh {
+        IndexedMesh::from_triangle_list(&self.as_normal_vertices())
+    }
+}
+
+impl Facet {
+    fn new(normal: [f32; 3], corners: [[f32; 3]; 3]) -> Facet {
+        let normal = Vec3::from(normal);
+        Facet {
+            normal: (normal.is_finite() && normal.length_squared() > 0.0)
+                .then(|| normal.normalize()),
+            corners,
+        }
+    }
+}
+
+// Every facet takes 50 bytes: the normal, three corners and a two-byte
+// attribute that is ignored.
+fn read_binary(bytes: &[u8]) -> Vec<Facet> {
+    bytes
+        .chunks_exact(50)
+        .map(|facet| {
+            let float = |i: usize| f32::from_le_bytes(facet[i * 4..i * 4 + 4].try_into().unwrap());
+            let vector = |first: usize| [float(first), float(first + 1), float(first + 2)];
+            Facet::new(vector(0), [vector(3), vector(6), vector(9)])
+        })
+        .collect()
+}
+
+// Returns the line of the error on failure.
+fn read_ascii(text: &str) -> Result<(String, Vec<Facet>), (usize, ObjError)> {
+    let mut name = None;
+    let mut facets = Vec::new();
+    let mut normal = [0.0; 3];
+    let mut corners: Vec<[f32; 3]> = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let fail = |e: ObjError| (line_index + 1, e);
+        let line = line.trim();
+        let (keyword, x) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match keyword {
+            "solid" => {
+                name.get_or_insert_with(|| x.trim().to_string());
+            }
+            "facet" => {
+                corners.clear();
+                normal = match x.trim().strip_prefix("normal") {
+                    Some(n) => parse_vector(n).map_err(fail)?,
+                    None => [0.0; 3],
+                };
+            }
+            "vertex" => corners.push(parse_vector(x).map_err(fail)?),
+            "endfacet" => {
+                let triangle: [[f32; 3]; 3] = corners
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| fail(ObjError::malformed(line)))?;
+                facets.push(Facet::new(normal, triangle));
+            }
+            _ => {}
+        }
+    }
+    Ok((name.unwrap_or_default(), facets))
+}
+
+fn parse_vector(x: &str) -> Result<[f32; 3], ObjError> {
+    let values = x
+        .split_whitespace()
+        .map(|v| v.parse().map_err(|_| ObjError::invalid_number(v)))
+        .collect::<Result<Vec<f32>, ObjError>>()?;
+    values.try_into().map_err(|_| ObjError::malformed(x.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+    // A single triangle facing +Z, counter-clockwise.
+    const CORNERS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn binary(header: &[u8], normal: [f32; 3]) -> Vec<u8> {
+        let mut stl = header.to_vec();
+        stl.resize(80, b' ');
+        stl.extend(1u32.to_le_bytes());
+        for vector in [normal].iter().chain(&CORNERS) {
+            vector.iter().for_each(|x| stl.extend(x.to_le_bytes()));
+        }
+        stl.extend([0, 0]);
+        stl
+    }
+
+    fn load(stl: &[u8]) -> Result<StlLoader, ObjError> {
+        StlLoader::from_reader(stl, "test.stl", COLOR, false)
+    }
+
+    fn positions(loader: &StlLoader) -> Vec<[f32; 3]> {
+        loader
+            .as_normal_vertices()
+            .iter()
+            .map(|v| v.position)
+            .collect()
+    }
+
+    #[test]
+    fn binary_facets_are_read() {
+        let loader = load(&binary(b"exported", [0.0, 0.0, 2.0])).unwrap();
+        assert_eq!(loader.name(), "");
+        assert_eq!(positions(&loader), CORNERS);
+        for vertex in loader.as_normal_vertices() {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+            assert_eq!(vertex.color, COLOR);
+        }
+        assert_eq!(loader.winding_order(), Some(Winding::CounterClockwise));
+
+        // A zero normal is replaced by the normal of the corners.
+        let loader = load(&binary(b"exported", [0.0; 3])).unwrap();
+        assert_eq!(loader.as_normal_vertices()[0].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn ascii_files_start_with_solid() {
+        let stl = "solid triangle\n\
+                   facet normal 0 0 1\n  outer loop\n\
+                   vertex 0 0 0\n    vertex 1 0 0\n    vertex 0 1 0\n\
+                   endloop\nendfacet\nendsolid triangle\n";
+        let loader = load(stl.as_bytes()).unwrap();
+        assert_eq!(loader.name(), "triangle");
+        assert_eq!(positions(&loader), CORNERS);
+        assert_eq!(loader.as_normal_vertices()[0].normal, [0.0, 0.0, 1.0]);
+
+        let error = load(stl.replace("vertex 0 1 0", "vertex 0 one 0").as_bytes())
+            .err()
+            .unwrap();
+        assert!(
+            matches!(error, ObjError::InvalidNumber { line: 6, .. }),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn binary_files_may_start_with_solid() {
+        let loader = load(&binary(b"solid exported by a CAD tool", [0.0, 0.0, 1.0])).unwrap();
+        assert_eq!(loader.name(), "");
+        assert_eq!(positions(&loader), CORNERS);
+
+        // Neither the size of a binary file nor ASCII
+        let error = load(&binary(b"exported", [0.0; 3])[..100]).err().unwrap();
+        assert!(matches!(error, ObjError::Unsupported { .. }), "{error}");
+    }
+}
@@ -0,0 +1,125 @@
+use glam::{Vec2, Vec3};
+
+/// Splits a simple polygon into triangles.
+///
+/// The returned triangles index into `points` and keep the winding order of
+/// the polygon. Convex polygons are split as a fan around the first corner,
+/// concave ones by ear clipping in the plane of the polygon.
+pub(super) fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    if count < 3 {
+        return Vec::new();
+    }
+    if count == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // The normal grows with the area of the polygon, so whether it is too
+    // short to project on is relative to the size of the polygon.
+    let normal = newell_normal(points);
+    if normal.length() <= f32::EPSILON * extent(points).powi(2) || is_convex(points, normal) {
+        return fan(count);
+    }
+
+    ear_clip(&project(points, normal))
+}
+
+fn fan(count: usize) -> Vec<[usize; 3]> {
+    (1..count - 1).map(|i| [0, i, i + 1]).collect()
+}
+
+// The length of the diagonal of the bounding box of `points`
+fn extent(points: &[Vec3]) -> f32 {
+    let (min, max) = points.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), &p| (min.min(p), max.max(p)),
+    );
+    (max - min).length()
+}
+
+// Newell's method gives a usable normal even for non-planar and concave
+// polygons, where the cross product of two edges may point the wrong way.
+fn newell_normal(points: &[Vec3]) -> Vec3 {
+    let mut normal = Vec3::ZERO;
+    for (i, current) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal
+}
+
+fn is_convex(points: &[Vec3], normal: Vec3) -> bool {
+    let count = points.len();
+    (0..count).all(|i| {
+        let prev = points[(i + count - 1) % count];
+        let current = points[i];
+        let next = points[(i + 1) % count];
+        (current - prev).cross(next - current).dot(normal) >= 0.0
+    })
+}
+
+// Drops the axis the polygon is most aligned with. The projected polygon
+// keeps a counter-clockwise orientation regardless of which axis is dropped.
+fn project(points: &[Vec3], normal: Vec3) -> Vec<Vec2> {
+    let abs = normal.abs();
+    let (flip, to_2d): (bool, fn(&Vec3) -> Vec2) = if abs.x >= abs.y && abs.x >= abs.z {
+        (normal.x < 0.0, |p| Vec2::new(p.y, p.z))
+    } else if abs.y >= abs.z {
+        (normal.y < 0.0, |p| Vec2::new(p.z, p.x))
+    } else {
+        (normal.z < 0.0, |p| Vec2::new(p.x, p.y))
+    };
+    points
+        .iter()
+        .map(to_2d)
+        .map(|p| if flip { Vec2::new(p.y, p.x) } else { p })
+        .collect()
+}
+
+fn ear_clip(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            );
+            is_ear(points, &remaining, a, b, c)
+        });
+        // Self-intersecting or badly degenerate input may have no ear left.
+        // Clipping an arbitrary corner still produces a closed surface.
+        let i = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+fn is_ear(points: &[Vec2], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    let (pa, pb, pc) = (points[a], points[b], points[c]);
+    if (pb - pa).perp_dot(pc - pb) <= 0.0 {
+        return false;
+    }
+    remaining
+        .iter()
+        .filter(|&&i| i != a && i != b && i != c)
+        .all(|&i| !in_triangle(points[i], pa, pb, pc))
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0
+}
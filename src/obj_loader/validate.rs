@@ -0,0 +1,680 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use glam::Vec3;
+
+use super::{mesh, Indices, Mesh, NormalVertex};
+
+/// The problems [`Mesh::validate`] found. Vertices and triangles are given
+/// by their index, a triangle `t` being made of the indices `3 * t..3 * t + 3`
+/// of [`Mesh::geometry`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Vertices with a NaN or infinite position, normal or texture
+    /// coordinate. [`Repair::renormalize`] fixes their normals, and
+    /// [`Repair::drop_degenerates`] removes them together with their
+    /// triangles otherwise.
+    pub non_finite_vertices: Vec<usize>,
+    /// Vertices whose normal is zero or not of unit length.
+    pub bad_normals: Vec<usize>,
+    /// Triangles without area, because two corners are the same or all
+    /// three lie on a line, or with a corner whose position or texture
+    /// coordinate isn't finite.
+    pub degenerate_triangles: Vec<usize>,
+    /// Triangles with the same corner positions as an earlier triangle, in
+    /// any order.
+    pub duplicate_triangles: Vec<usize>,
+    /// Edges shared by two triangles that both run along it in the same
+    /// direction, so that one of them faces the other way.
+    pub inconsistent_edges: usize,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        *self == ValidationReport::default()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "no problems found");
+        }
+        let problems = [
+            (
+                self.non_finite_vertices.len(),
+                "vertices with NaN or infinite values",
+            ),
+            (
+                self.bad_normals.len(),
+                "vertices with a zero or non-unit normal",
+            ),
+            (self.degenerate_triangles.len(), "degenerate triangles"),
+            (self.duplicate_triangles.len(), "duplicate triangles"),
+            (self.inconsistent_edges, "edges with inconsistent winding"),
+        ];
+        let found: Vec<String> = problems
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, problem)| format!("{} {}", count, problem))
+            .collect();
+        write!(f, "{}", found.join(", "))
+    }
+}
+
+/// The fixes applied by [`super::Model::repair`]. Nothing is repaired
+/// unless enabled.
+///
+/// The repairs run in a fixed order: welding, dropping degenerate and then
+/// duplicate triangles, renormalizing and finally fixing the winding order.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Repair {
+    weld_distance: Option<f32>,
+    drop_degenerates: bool,
+    drop_duplicates: bool,
+    renormalize: bool,
+    fix_winding: bool,
+}
+
+impl Repair {
+    pub fn new() -> Repair {
+        Repair::default()
+    }
+
+    /// Every repair, welding vertices closer than `1e-5`.
+    pub fn all() -> Repair {
+        Repair::new()
+            .weld(1e-5)
+            .drop_degenerates()
+            .drop_duplicates()
+            .renormalize()
+            .fix_winding()
+    }
+
+    /// Merges vertices whose positions are at most `distance` apart and
+    /// whose normals, colors and texture coordinates match to within
+    /// `1e-4`. Vertices no triangle uses any more are removed.
+    pub fn weld(mut self, distance: f32) -> Repair {
+        self.weld_distance = Some(distance);
+        self
+    }
+
+    /// Drops the triangles listed in
+    /// [`ValidationReport::degenerate_triangles`]. Vertices no triangle uses
+    /// any more are removed.
+    pub fn drop_degenerates(mut self) -> Repair {
+        self.drop_degenerates = true;
+        self
+    }
+
+    /// Keeps only the first of several triangles with the same corners.
+    /// Vertices no triangle uses any more are removed.
+    pub fn drop_duplicates(mut self) -> Repair {
+        self.drop_duplicates = true;
+        self
+    }
+
+    /// Scales normals to unit length. Zero and non-finite normals are
+    /// replaced by the area-weighted normal of the triangles around them.
+    pub fn renormalize(mut self) -> Repair {
+        self.renormalize = true;
+        self
+    }
+
+    /// Flips triangles so that every connected part of the mesh has one
+    /// winding order, the one most of its triangles already had.
+    pub fn fix_winding(mut self) -> Repair {
+        self.fix_winding = true;
+        self
+    }
+}
+
+/// What [`super::Model::repair`] changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    pub welded_vertices: usize,
+    pub dropped_degenerates: usize,
+    pub dropped_duplicates: usize,
+    pub renormalized: usize,
+    pub flipped_triangles: usize,
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == RepairReport::default() {
+            return write!(f, "nothing to repair");
+        }
+        let changes = [
+            (self.welded_vertices, "welded", "vertices"),
+            (self.dropped_degenerates, "dropped", "degenerate triangles"),
+            (self.dropped_duplicates, "dropped", "duplicate triangles"),
+            (self.renormalized, "renormalized", "normals"),
+            (self.flipped_triangles, "flipped", "triangles"),
+        ];
+        let done: Vec<String> = changes
+            .iter()
+            .filter(|(count, _, _)| *count > 0)
+            .map(|(count, action, what)| format!("{} {} {}", action, count, what))
+            .collect();
+        write!(f, "{}", done.join(", "))
+    }
+}
+
+// How much the normals, colors and texture coordinates of welded vertices
+// may differ
+const ATTRIBUTE_TOLERANCE: f32 = 1e-4;
+
+impl Mesh {
+    /// Looks for geometry that renders wrong or breaks algorithms working on
+    /// the mesh, without changing anything. See [`super::Model::repair`] to
+    /// fix what it finds.
+    pub fn validate(&self) -> ValidationReport {
+        let vertices = &self.geometry.vertices;
+        let mut report = ValidationReport::default();
+        for (i, vertex) in vertices.iter().enumerate() {
+            let normal = Vec3::from(vertex.normal);
+            let finite = Vec3::from(vertex.position).is_finite()
+                && normal.is_finite()
+                && self.uvs[i].iter().all(|x| x.is_finite());
+            if !finite {
+                report.non_finite_vertices.push(i);
+            } else if (normal.length() - 1.0).abs() > 1e-3 {
+                report.bad_normals.push(i);
+            }
+        }
+
+        let triangles = triangles(&self.geometry.indices);
+        let mut seen = HashSet::new();
+        let mut valid = Vec::new();
+        for (t, triangle) in triangles.iter().enumerate() {
+            if is_degenerate(vertices, &self.uvs, *triangle) {
+                report.degenerate_triangles.push(t);
+            } else if !seen.insert(corner_set(vertices, *triangle)) {
+                report.duplicate_triangles.push(t);
+            } else {
+                valid.push(*triangle);
+            }
+        }
+        report.inconsistent_edges = neighbours(vertices, &valid)
+            .iter()
+            .filter(|(_, _, same_direction)| *same_direction)
+            .count();
+        report
+    }
+
+    pub(super) fn repair(&mut self, repair: Repair) -> RepairReport {
+        let mut report = RepairReport::default();
+        // Every triangle with the sub-mesh it belongs to, or `usize::MAX`
+        let mut triangles: Vec<([usize; 3], usize)> = triangles(&self.geometry.indices)
+            .into_iter()
+            .enumerate()
+            .map(|(t, triangle)| {
+                let first = t as u32 * 3;
+                let sub_mesh = self
+                    .sub_meshes
+                    .iter()
+                    .position(|s| s.indices.contains(&first));
+                (triangle, sub_mesh.unwrap_or(usize::MAX))
+            })
+            .collect();
+
+        if let Some(distance) = repair.weld_distance {
+            let remap = weld(&self.geometry.vertices, &self.uvs, distance);
+            report.welded_vertices = remap.iter().enumerate().filter(|(i, r)| i != *r).count();
+            for (triangle, _) in &mut triangles {
+                *triangle = triangle.map(|i| remap[i]);
+            }
+        }
+        let vertices = &self.geometry.vertices;
+        if repair.drop_degenerates {
+            let before = triangles.len();
+            triangles.retain(|(triangle, _)| !is_degenerate(vertices, &self.uvs, *triangle));
+            report.dropped_degenerates = before - triangles.len();
+        }
+        if repair.drop_duplicates {
+            let before = triangles.len();
+            let mut seen = HashSet::new();
+            triangles.retain(|(triangle, _)| seen.insert(corner_set(vertices, *triangle)));
+            report.dropped_duplicates = before - triangles.len();
+        }
+        if repair.renormalize {
+            let corners: Vec<[usize; 3]> = triangles.iter().map(|(t, _)| *t).collect();
+            report.renormalized = renormalize(&mut self.geometry.vertices, &corners);
+        }
+        if repair.fix_winding {
+            let corners: Vec<[usize; 3]> = triangles.iter().map(|(t, _)| *t).collect();
+            for t in winding_flips(&self.geometry.vertices, &corners) {
+                triangles[t].0.swap(1, 2);
+                report.flipped_triangles += 1;
+            }
+        }
+        if report == RepairReport::default() {
+            return report;
+        }
+
+        // Sub-meshes keep their order and stay contiguous.
+        let mut indices: Vec<u32> = Vec::with_capacity(triangles.len() * 3);
+        let owners = (0..self.sub_meshes.len()).chain([usize::MAX]);
+        for owner in owners {
+            let start = indices.len() as u32;
+            for (triangle, _) in triangles.iter().filter(|(_, o)| *o == owner) {
+                indices.extend(triangle.map(|i| i as u32));
+            }
+            if let Some(sub_mesh) = self.sub_meshes.get_mut(owner) {
+                sub_mesh.indices = start..indices.len() as u32;
+            }
+        }
+        // Dropped triangles may leave vertices behind, which would still
+        // count towards the bounds, e.g. with a NaN position.
+        let dropped = report.dropped_degenerates + report.dropped_duplicates;
+        if repair.weld_distance.is_some() || dropped > 0 {
+            self.remove_unused(&mut indices);
+        }
+        let vertex_count = self.geometry.vertices.len();
+        self.geometry.indices = Indices::new(indices, vertex_count);
+        (self.bounds, self.sphere) = mesh::bounds_of(&self.geometry.vertices);
+        self.generation += 1;
+        report
+    }
+
+    fn remove_unused(&mut self, indices: &mut [u32]) {
+        let mut used = vec![false; self.geometry.vertices.len()];
+        indices.iter().for_each(|i| used[*i as usize] = true);
+        let mut remap = vec![0; used.len()];
+        let mut next = 0;
+        for (i, used) in used.iter().enumerate() {
+            remap[i] = next;
+            next += *used as u32;
+        }
+        indices.iter_mut().for_each(|i| *i = remap[*i as usize]);
+        let keep = |i: &usize| used[*i];
+        let count = used.len();
+        self.geometry.vertices = (0..count)
+            .filter(keep)
+            .map(|i| self.geometry.vertices[i])
+            .collect();
+        self.uvs = (0..count).filter(keep).map(|i| self.uvs[i]).collect();
+        if !self.tangents.is_empty() {
+            self.tangents = (0..count).filter(keep).map(|i| self.tangents[i]).collect();
+        }
+    }
+}
+
+fn triangles(indices: &Indices) -> Vec<[usize; 3]> {
+    (0..indices.len() / 3)
+        .map(|t| [0, 1, 2].map(|c| indices.get(t * 3 + c).unwrap()))
+        .collect()
+}
+
+fn position(vertices: &[NormalVertex], i: usize) -> Vec3 {
+    Vec3::from(vertices[i].position)
+}
+
+// Compares positions by value, with 0.0 and -0.0 being the same.
+fn position_key(vertices: &[NormalVertex], i: usize) -> [u32; 3] {
+    vertices[i].position.map(|x| (x + 0.0).to_bits())
+}
+
+fn corner_set(vertices: &[NormalVertex], triangle: [usize; 3]) -> [[u32; 3]; 3] {
+    let mut corners = triangle.map(|i| position_key(vertices, i));
+    corners.sort();
+    corners
+}
+
+// Slivers whose area is tiny compared to their longest edge count as
+// degenerate too, since their normal is mostly rounding error.
+fn is_degenerate(vertices: &[NormalVertex], uvs: &[[f32; 2]], [a, b, c]: [usize; 3]) -> bool {
+    let [p, q, r] = [a, b, c].map(|i| position(vertices, i));
+    let finite_uvs = [a, b, c]
+        .iter()
+        .all(|i| uvs[*i].iter().all(|x| x.is_finite()));
+    let finite = p.is_finite() && q.is_finite() && r.is_finite() && finite_uvs;
+    if a == b || b == c || c == a || !finite {
+        return true;
+    }
+    let longest = (q - p)
+        .length_squared()
+        .max((r - q).length_squared())
+        .max((p - r).length_squared());
+    (q - p).cross(r - p).length() <= longest * 1e-6
+}
+
+// The corner positions of an edge, the smaller one first
+type Edge = ([u32; 3], [u32; 3]);
+
+// Every pair of triangles sharing an edge, by position, with whether both
+// run along the edge in the same direction. Edges used by more than two
+// triangles don't connect any of them.
+fn neighbours(vertices: &[NormalVertex], triangles: &[[usize; 3]]) -> Vec<(usize, usize, bool)> {
+    let mut edges: HashMap<Edge, Vec<(usize, bool)>> = HashMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        for corner in 0..3 {
+            let from = position_key(vertices, triangle[corner]);
+            let to = position_key(vertices, triangle[(corner + 1) % 3]);
+            let (key, forward) = if from < to {
+                ((from, to), true)
+            } else {
+                ((to, from), false)
+            };
+            edges.entry(key).or_default().push((t, forward));
+        }
+    }
+    edges
+        .values()
+        .filter_map(|users| match users.as_slice() {
+            [(a, a_forward), (b, b_forward)] => Some((*a, *b, a_forward == b_forward)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Returns for every vertex the vertex it is merged into, which is itself for
+// vertices that are kept.
+fn weld(vertices: &[NormalVertex], uvs: &[[f32; 2]], distance: f32) -> Vec<usize> {
+    // Cells smaller than the rounding error of the positions only slow the
+    // search down, and would overflow the cell coordinates.
+    let extent = vertices
+        .iter()
+        .map(|v| Vec3::from(v.position).abs().max_element())
+        .filter(|x| x.is_finite())
+        .fold(0.0, f32::max);
+    let cell_size = distance.max(extent * f32::EPSILON).max(f32::MIN_POSITIVE);
+    let cell = |p: Vec3| (p / cell_size).floor().to_array().map(|x| x as i64);
+    let close = |a: &[f32], b: &[f32], tolerance: f32| {
+        a.iter().zip(b).all(|(x, y)| (x - y).abs() <= tolerance)
+    };
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut remap: Vec<usize> = (0..vertices.len()).collect();
+    for (i, vertex) in vertices.iter().enumerate() {
+        let p = Vec3::from(vertex.position);
+        if !p.is_finite() {
+            continue;
+        }
+        let [x, y, z] = cell(p);
+        let mut neighbours = (-1..=1).flat_map(|dx| {
+            (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [x + dx, y + dy, z + dz]))
+        });
+        let kept = neighbours.find_map(|key| {
+            cells.get(&key)?.iter().copied().find(|&k| {
+                let other = &vertices[k];
+                p.distance(Vec3::from(other.position)) <= distance
+                    && close(&vertex.normal, &other.normal, ATTRIBUTE_TOLERANCE)
+                    && close(&vertex.color, &other.color, ATTRIBUTE_TOLERANCE)
+                    && close(&uvs[i], &uvs[k], ATTRIBUTE_TOLERANCE)
+            })
+        });
+        match kept {
+            Some(k) => remap[i] = k,
+            None => cells.entry([x, y, z]).or_default().push(i),
+        }
+    }
+    remap
+}
+
+// Returns how many normals were changed.
+fn renormalize(vertices: &mut [NormalVertex], triangles: &[[usize; 3]]) -> usize {
+    let is_bad = |v: &NormalVertex| {
+        let normal = Vec3::from(v.normal);
+        !normal.is_finite() || normal.length_squared() == 0.0
+    };
+    let bad: Vec<bool> = vertices.iter().map(is_bad).collect();
+    let mut sums = vec![Vec3::ZERO; vertices.len()];
+    for triangle in triangles {
+        if !triangle.iter().any(|i| bad[*i]) {
+            continue;
+        }
+        let [p, q, r] = triangle.map(|i| position(vertices, i));
+        // Faces are oriented like the normals of their other corners, or
+        // like the default of `ModelBuilder`, which turns front faces
+        // clockwise, if they have none.
+        let face = (q - p).cross(r - p);
+        let known: Vec3 = triangle
+            .iter()
+            .filter(|i| !bad[**i])
+            .map(|i| Vec3::from(vertices[*i].normal))
+            .sum();
+        let face = if known == Vec3::ZERO || face.dot(known) < 0.0 {
+            -face
+        } else {
+            face
+        };
+        for i in triangle.iter().filter(|i| bad[**i]) {
+            sums[*i] += face;
+        }
+    }
+
+    let mut changed = 0;
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = if bad[i] {
+            sums[i].normalize_or_zero()
+        } else {
+            Vec3::from(vertex.normal).normalize()
+        };
+        if normal.to_array() != vertex.normal {
+            vertex.normal = normal.to_array();
+            changed += 1;
+        }
+    }
+    changed
+}
+
+// The triangles to flip so that every connected part is wound the same way.
+fn winding_flips(vertices: &[NormalVertex], triangles: &[[usize; 3]]) -> Vec<usize> {
+    let mut links: Vec<Vec<(usize, bool)>> = vec![Vec::new(); triangles.len()];
+    for (a, b, same_direction) in neighbours(vertices, triangles) {
+        links[a].push((b, same_direction));
+        links[b].push((a, same_direction));
+    }
+    // Flip states relative to the first triangle of each part
+    let mut flip: Vec<Option<bool>> = vec![None; triangles.len()];
+    let mut flips = Vec::new();
+    for seed in 0..triangles.len() {
+        if flip[seed].is_some() {
+            continue;
+        }
+        flip[seed] = Some(false);
+        let mut part = vec![seed];
+        let mut queue = VecDeque::from([seed]);
+        while let Some(t) = queue.pop_front() {
+            let flipped = flip[t].unwrap();
+            for (other, same_direction) in &links[t] {
+                // Parts that can't be oriented, like a Möbius strip, keep
+                // the first state found.
+                if flip[*other].is_none() {
+                    flip[*other] = Some(flipped ^ same_direction);
+                    part.push(*other);
+                    queue.push_back(*other);
+                }
+            }
+        }
+        let flipped = part.iter().filter(|t| flip[**t] == Some(true)).count();
+        let majority = flipped * 2 > part.len();
+        flips.extend(part.into_iter().filter(|t| flip[*t] != Some(majority)));
+    }
+    flips.sort();
+    flips
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+    use crate::obj_loader::{IndexedMesh, SubMesh};
+
+    const UP: [f32; 3] = [0.0, 0.0, 1.0];
+
+    fn mesh(vertices: &[([f32; 3], [f32; 3])], triangles: &[[u32; 3]]) -> Mesh {
+        let vertices: Vec<NormalVertex> = vertices
+            .iter()
+            .map(|(position, normal)| NormalVertex {
+                position: *position,
+                normal: *normal,
+                color: [1.0; 3],
+            })
+            .collect();
+        let indices: Vec<u32> = triangles.iter().flatten().copied().collect();
+        let (bounds, sphere) = mesh::bounds_of(&vertices);
+        Mesh {
+            uvs: vec![[0.0; 2]; vertices.len()],
+            tangents: Vec::new(),
+            sub_meshes: vec![SubMesh {
+                object: String::new(),
+                group: String::new(),
+                material: None,
+                indices: 0..indices.len() as u32,
+                transform: Mat4::IDENTITY,
+                visible: true,
+            }],
+            materials: Vec::new(),
+            bounds,
+            sphere,
+            generation: 0,
+            id: mesh::MeshId::new(),
+            geometry: IndexedMesh {
+                indices: Indices::new(indices, vertices.len()),
+                vertices,
+            },
+        }
+    }
+
+    fn indices(mesh: &Mesh) -> Vec<usize> {
+        mesh.geometry.indices.iter().collect()
+    }
+
+    #[test]
+    fn flipped_neighbours_are_turned_around() {
+        let corners = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        let vertices = corners.map(|p| (p, UP));
+        // Both triangles run from vertex 0 to 2 along the shared diagonal.
+        let mut square = mesh(&vertices, &[[0, 1, 2], [0, 2, 3]]);
+        assert!(square.validate().is_valid());
+        let mut flipped = mesh(&vertices, &[[0, 1, 2], [2, 0, 3]]);
+        assert_eq!(
+            flipped.validate(),
+            ValidationReport {
+                inconsistent_edges: 1,
+                ..ValidationReport::default()
+            }
+        );
+
+        let repaired = flipped.repair(Repair::new().fix_winding());
+        assert_eq!(repaired.flipped_triangles, 1);
+        assert_eq!(indices(&flipped), [0, 1, 2, 2, 3, 0]);
+        assert!(flipped.validate().is_valid());
+        assert_eq!(square.repair(Repair::all()), RepairReport::default());
+    }
+
+    #[test]
+    fn only_vertices_within_the_distance_are_welded() {
+        let vertices = [
+            ([0.0, 0.0, 0.0], UP),
+            ([1.0, 0.0, 0.0], UP),
+            ([0.0, 1.0, 0.0], UP),
+            // A copy of vertex 1 within the distance, and one of vertex 2
+            // outside of it
+            ([1.0 + 1e-6, 0.0, 0.0], UP),
+            ([0.0, 1.0 + 1e-3, 0.0], UP),
+            ([1.0, 1.0, 0.0], UP),
+        ];
+        let mut mesh = mesh(&vertices, &[[0, 1, 2], [3, 5, 4]]);
+        let report = mesh.repair(Repair::new().weld(1e-5));
+        assert_eq!(report.welded_vertices, 1);
+        // Vertex 3 is gone, so the ones after it move down.
+        assert_eq!(indices(&mesh), [0, 1, 2, 1, 4, 3]);
+        assert_eq!(mesh.geometry.vertices.len(), 5);
+        assert_eq!(mesh.uvs.len(), 5);
+        assert_eq!(mesh.sub_meshes[0].indices, 0..6);
+    }
+
+    #[test]
+    fn welding_far_from_the_origin_does_not_overflow() {
+        for far in [1e30, f32::MAX] {
+            let vertices = [
+                ([far, 0.0, 0.0], UP),
+                ([far, far, 0.0], UP),
+                ([-far, far, 0.0], UP),
+                ([far, far, 0.0], UP),
+            ];
+            let mut mesh = mesh(&vertices, &[[0, 1, 2], [0, 3, 2]]);
+            let report = mesh.repair(Repair::new().weld(0.0));
+            assert_eq!(report.welded_vertices, 1);
+            assert_eq!(indices(&mesh), [0, 1, 2, 0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn zero_area_triangles_are_dropped() {
+        let vertices = [
+            ([0.0, 0.0, 0.0], UP),
+            ([1.0, 0.0, 0.0], UP),
+            ([0.0, 1.0, 0.0], UP),
+            ([2.0, 0.0, 0.0], UP),
+        ];
+        // On a line, and with a repeated corner
+        let mut mesh = mesh(&vertices, &[[0, 1, 3], [0, 1, 2], [2, 2, 1]]);
+        let report = mesh.validate();
+        assert_eq!(report.degenerate_triangles, [0, 2]);
+        assert_eq!(report.to_string(), "2 degenerate triangles");
+
+        let repaired = mesh.repair(Repair::new().drop_degenerates());
+        assert_eq!(repaired.dropped_degenerates, 2);
+        assert_eq!(indices(&mesh), [0, 1, 2]);
+        assert_eq!(mesh.sub_meshes[0].indices, 0..3);
+        assert!(mesh.validate().is_valid());
+    }
+
+    #[test]
+    fn broken_normals_are_recomputed() {
+        let vertices = [
+            ([0.0, 0.0, 0.0], UP),
+            ([1.0, 0.0, 0.0], [f32::NAN, 0.0, 0.0]),
+            ([0.0, 1.0, 0.0], [0.0, 0.0, 2.0]),
+        ];
+        let mut mesh = mesh(&vertices, &[[0, 1, 2]]);
+        let report = mesh.validate();
+        assert_eq!(report.non_finite_vertices, [1]);
+        assert_eq!(report.bad_normals, [2]);
+        assert!(report.degenerate_triangles.is_empty());
+
+        let repaired = mesh.repair(Repair::new().renormalize());
+        assert_eq!(repaired.renormalized, 2);
+        // The NaN normal takes the side of the normals next to it.
+        for vertex in &mesh.geometry.vertices {
+            assert_eq!(vertex.normal, UP);
+        }
+        assert!(mesh.validate().is_valid());
+    }
+
+    #[test]
+    fn non_finite_input_is_fully_repaired() {
+        let vertices = [
+            ([0.0, 0.0, 0.0], UP),
+            ([1.0, 0.0, 0.0], [0.0, f32::INFINITY, 0.0]),
+            ([0.0, 1.0, 0.0], UP),
+            ([f32::NAN, 0.0, 0.0], UP),
+            ([1.0, 1.0, 0.0], UP),
+            ([2.0, 0.0, 0.0], UP),
+        ];
+        let mut mesh = mesh(&vertices, &[[0, 1, 2], [0, 3, 2], [1, 4, 2], [1, 5, 4]]);
+        mesh.uvs[5] = [0.0, f32::NAN];
+        let report = mesh.validate();
+        assert_eq!(report.non_finite_vertices, [1, 3, 5]);
+        assert_eq!(report.degenerate_triangles, [1, 3]);
+
+        let repaired = mesh.repair(Repair::all());
+        assert_eq!(repaired.dropped_degenerates, 2);
+        assert_eq!(repaired.renormalized, 1);
+        assert!(mesh.validate().is_valid(), "{}", mesh.validate());
+        assert_eq!(mesh.geometry.vertices.len(), 4);
+        assert_eq!(mesh.uvs.len(), 4);
+        assert_eq!(mesh.bounds().min, Vec3::ZERO);
+        assert_eq!(mesh.bounds().max, Vec3::new(1.0, 1.0, 0.0));
+        assert!(mesh.bounding_sphere().radius.is_finite());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+/// The order in which the corners of a face appear when it is seen from the
+/// front, i.e. from the side its normal points to.
+///
+/// The renderer draws faces that are clockwise in model space, so
+/// counter-clockwise models, like those exported by Blender or described by
+/// glTF, are inverted when they are loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl Winding {
+    /// Whether faces with this winding order have to be reversed for the
+    /// renderer.
+    pub fn needs_invert(self) -> bool {
+        self == Winding::CounterClockwise
+    }
+}
+
+/// Which faces of a model the renderer skips, see [`super::Model::set_cull_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CullMode {
+    /// Faces seen from behind are skipped, which is right for closed
+    /// meshes, whose back faces are hidden anyway.
+    #[default]
+    Back,
+    /// Both sides of every face are drawn, for open meshes like planes and
+    /// leaves, or models whose winding order is mixed up.
+    None,
+}
+
+/// Guesses the winding order of `triangles`, given in the order of the file.
+///
+/// Triangles with a normal from the file vote on whether their corners turn
+/// counter-clockwise around it. Without a majority, closed meshes are
+/// counter-clockwise if they enclose a positive volume. Open meshes without
+/// normals give `None`.
+pub(super) fn detect(
+    positions: &[Vec3],
+    triangles: &[[usize; 3]],
+    normals: &[Option<Vec3>],
+) -> Option<Winding> {
+    let (mut agree, mut disagree) = (0, 0);
+    for (triangle, normal) in triangles.iter().zip(normals) {
+        let normal = match normal {
+            Some(normal) => *normal,
+            None => continue,
+        };
+        let [a, b, c] = triangle.map(|i| positions[i]);
+        let dot = (b - a).cross(c - a).dot(normal);
+        if dot > 0.0 {
+            agree += 1;
+        } else if dot < 0.0 {
+            disagree += 1;
+        }
+    }
+    if agree != disagree {
+        return Some(match agree > disagree {
+            true => Winding::CounterClockwise,
+            false => Winding::Clockwise,
+        });
+    }
+    if !is_closed(positions, triangles) {
+        return None;
+    }
+
+    // Six times the signed volume, which only depends on the orientation
+    // of the surface for closed meshes
+    let (mut volume, mut magnitude) = (0.0, 0.0);
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|i| positions[i]);
+        let part = a.dot(b.cross(c));
+        volume += part;
+        magnitude += part.abs();
+    }
+    // Flat meshes, like a quad with both sides, enclose nothing.
+    if volume.abs() <= magnitude * 1e-4 {
+        return None;
+    }
+    Some(match volume > 0.0 {
+        true => Winding::CounterClockwise,
+        false => Winding::Clockwise,
+    })
+}
+
+// Whether every edge, compared by position, is shared by exactly two
+// triangles.
+fn is_closed(positions: &[Vec3], triangles: &[[usize; 3]]) -> bool {
+    let key = |i: usize| positions[i].to_array().map(|x| (x + 0.0).to_bits());
+    let mut edges: HashMap<([u32; 3], [u32; 3]), u32> = HashMap::new();
+    for triangle in triangles {
+        for corner in 0..3 {
+            let from = key(triangle[corner]);
+            let to = key(triangle[(corner + 1) % 3]);
+            *edges.entry((from.min(to), from.max(to))).or_default() += 1;
+        }
+    }
+    !edges.is_empty() && edges.values().all(|count| *count == 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A unit cube with every face counter-clockwise seen from outside.
+    fn cube() -> (Vec<Vec3>, Vec<[usize; 3]>) {
+        let positions = (0..8)
+            .map(|i| Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2 & 1) as f32))
+            .collect();
+        let quads = [
+            [0, 2, 3, 1], // -Z
+            [4, 5, 7, 6], // +Z
+            [0, 1, 5, 4], // -Y
+            [2, 6, 7, 3], // +Y
+            [0, 4, 6, 2], // -X
+            [1, 3, 7, 5], // +X
+        ];
+        let triangles = quads
+            .iter()
+            .flat_map(|[a, b, c, d]| [[*a, *b, *c], [*a, *c, *d]])
+            .collect();
+        (positions, triangles)
+    }
+
+    fn reversed(triangles: &[[usize; 3]]) -> Vec<[usize; 3]> {
+        triangles.iter().map(|[a, b, c]| [*a, *c, *b]).collect()
+    }
+
+    #[test]
+    fn normals_vote() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::ONE];
+        let triangles = [[0, 1, 2], [1, 3, 2]];
+        let up = [Some(Vec3::Z), Some(Vec3::Z)];
+        assert_eq!(
+            detect(&positions, &triangles, &up),
+            Some(Winding::CounterClockwise)
+        );
+        let down = [Some(-Vec3::Z), Some(-Vec3::Z)];
+        assert_eq!(
+            detect(&positions, &triangles, &down),
+            Some(Winding::Clockwise)
+        );
+        // Triangles without a normal don't vote.
+        let one = [Some(-Vec3::Z), None];
+        assert_eq!(
+            detect(&positions, &triangles, &one),
+            Some(Winding::Clockwise)
+        );
+
+        // Normals outvote the volume of a closed mesh.
+        let (positions, triangles) = cube();
+        let inward: Vec<Option<Vec3>> = triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| positions[i]);
+                Some(-(b - a).cross(c - a))
+            })
+            .collect();
+        assert_eq!(
+            detect(&positions, &triangles, &inward),
+            Some(Winding::Clockwise)
+        );
+    }
+
+    #[test]
+    fn closed_meshes_without_normals_use_their_volume() {
+        let (positions, triangles) = cube();
+        let none = vec![None; triangles.len()];
+        assert_eq!(
+            detect(&positions, &triangles, &none),
+            Some(Winding::CounterClockwise)
+        );
+        assert_eq!(
+            detect(&positions, &reversed(&triangles), &none),
+            Some(Winding::Clockwise)
+        );
+
+        // Away from the origin, tied votes fall back to the volume too.
+        let moved: Vec<Vec3> = positions.iter().map(|p| *p + Vec3::splat(10.0)).collect();
+        let mut tied = none.clone();
+        tied[0] = Some(Vec3::Z);
+        tied[1] = Some(-Vec3::Z);
+        assert_eq!(
+            detect(&moved, &triangles, &tied),
+            Some(Winding::CounterClockwise)
+        );
+    }
+
+    #[test]
+    fn open_or_flat_meshes_without_normals_are_undecided() {
+        let (positions, triangles) = cube();
+        let open = &triangles[2..];
+        assert_eq!(detect(&positions, open, &vec![None; open.len()]), None);
+
+        // A quad with both sides is closed but encloses nothing.
+        let quad = [[0, 2, 3], [0, 3, 1]];
+        let both_sides = [quad.to_vec(), reversed(&quad)].concat();
+        assert_eq!(detect(&positions, &both_sides, &[None; 4]), None);
+        assert_eq!(detect(&positions, &[], &[]), None);
+    }
+}
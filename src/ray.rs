@@ -0,0 +1,186 @@
+use glam::{Mat4, Vec3};
+
+use crate::obj_loader::{Aabb, Sphere};
+
+/// A half-line starting at `origin`, for picking objects under the mouse
+/// with [`crate::mvp::VP::screen_ray`] and
+/// [`crate::obj_loader::Model::raycast`].
+///
+/// Distances along the ray are measured in lengths of `direction`, which is
+/// unit length for rays made with [`Ray::new`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+/// Where a ray hit a model, see [`crate::obj_loader::Model::raycast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// Distance from the origin of the ray.
+    pub distance: f32,
+    /// The hit point in world space.
+    pub position: Vec3,
+    /// Index of the triangle in the model's index buffer, divided by three.
+    pub triangle: usize,
+    /// Weights of the three corners of the triangle at the hit point, in
+    /// the order of the index buffer.
+    pub barycentric: Vec3,
+    /// The vertex normals interpolated at the hit point, in world space.
+    pub normal: Vec3,
+}
+
+impl Ray {
+    /// A ray from `origin` towards `direction`, which is normalized.
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// The point `distance` along the ray.
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// The ray after an affine `transform`. The direction is not normalized
+    /// again, so every point stays at the same distance along the ray.
+    pub fn transformed(&self, transform: Mat4) -> Ray {
+        Ray {
+            origin: transform.transform_point3(self.origin),
+            direction: transform.transform_vector3(self.direction),
+        }
+    }
+
+    /// The distance at which the ray enters `aabb`, 0 if it starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        // Slab test: the ray is inside the box where it is between the two
+        // planes of every axis. Division by zero gives infinities, which
+        // keep axes the ray is parallel to out of the way.
+        let inverse = self.direction.recip();
+        let to_min = (aabb.min - self.origin) * inverse;
+        let to_max = (aabb.max - self.origin) * inverse;
+        let enter = to_min.min(to_max).max_element().max(0.0);
+        let exit = to_min.max(to_max).min_element();
+        (enter <= exit).then_some(enter)
+    }
+
+    /// The distance at which the ray enters `sphere`, 0 if it starts inside.
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let to_center = sphere.center - self.origin;
+        let a = self.direction.length_squared();
+        let b = to_center.dot(self.direction);
+        let c = to_center.length_squared() - sphere.radius * sphere.radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 || a == 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let exit = (b + root) / a;
+        if exit < 0.0 {
+            return None;
+        }
+        Some(((b - root) / a).max(0.0))
+    }
+
+    /// The distance at which the ray crosses the triangle `a`, `b`, `c`
+    /// from either side, with the barycentric weights of the corners at
+    /// that point.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, Vec3)> {
+        // Möller-Trumbore
+        let ab = b - a;
+        let ac = c - a;
+        let p = self.direction.cross(ac);
+        let determinant = ab.dot(p);
+        let scale = self.direction.length() * ab.length() * ac.length();
+        if determinant.abs() <= f32::EPSILON * scale {
+            // Parallel to the triangle, or a degenerate triangle
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let to_origin = self.origin - a;
+        let u = to_origin.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = to_origin.cross(ab);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = ac.dot(q) * inverse;
+        (distance >= 0.0).then_some((distance, Vec3::new(1.0 - u - v, u, v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The unit triangle in the XY plane
+    const A: Vec3 = Vec3::ZERO;
+    const B: Vec3 = Vec3::X;
+    const C: Vec3 = Vec3::Y;
+
+    #[test]
+    fn triangle_hits_have_barycentric_weights() {
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 2.0), -Vec3::Z);
+        let (distance, barycentric) = ray.intersect_triangle(A, B, C).unwrap();
+        assert_eq!(distance, 2.0);
+        assert!(barycentric.abs_diff_eq(Vec3::new(0.25, 0.25, 0.5), 1e-6));
+        let point = A * barycentric.x + B * barycentric.y + C * barycentric.z;
+        assert!(point.abs_diff_eq(ray.at(distance), 1e-6));
+
+        // Both sides are hit.
+        let below = Ray::new(Vec3::new(0.25, 0.5, -3.0), Vec3::Z);
+        assert_eq!(below.intersect_triangle(A, B, C).unwrap().0, 3.0);
+    }
+
+    #[test]
+    fn triangle_misses() {
+        // Beside the triangle, behind the origin, and parallel to it
+        let beside = Ray::new(Vec3::new(0.75, 0.75, 1.0), -Vec3::Z);
+        let behind = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::Z);
+        let parallel = Ray::new(Vec3::new(-1.0, 0.25, 0.0), Vec3::X);
+        let above = Ray::new(Vec3::new(-1.0, 0.25, 1.0), Vec3::X);
+        for ray in [beside, behind, parallel, above] {
+            assert_eq!(ray.intersect_triangle(A, B, C), None, "{ray:?}");
+        }
+    }
+
+    #[test]
+    fn rays_starting_inside_hit_at_zero() {
+        let aabb = Aabb {
+            min: Vec3::splat(-1.0),
+            max: Vec3::splat(1.0),
+        };
+        let sphere = Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let inside = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(inside.intersect_aabb(&aabb), Some(0.0));
+        assert_eq!(inside.intersect_sphere(&sphere), Some(0.0));
+
+        let outside = Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+        assert_eq!(outside.intersect_aabb(&aabb), Some(4.0));
+        assert_eq!(outside.intersect_sphere(&sphere), Some(4.0));
+        let away = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z);
+        assert_eq!(away.intersect_aabb(&aabb), None);
+        assert_eq!(away.intersect_sphere(&sphere), None);
+        // Parallel to the X slabs, outside of them
+        let past = Ray::new(Vec3::new(2.0, 0.0, 5.0), -Vec3::Z);
+        assert_eq!(past.intersect_aabb(&aabb), None);
+        assert_eq!(past.intersect_sphere(&sphere), None);
+    }
+
+    #[test]
+    fn transformed_rays_keep_distances() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+        let local = ray.transformed(Mat4::from_scale(Vec3::splat(0.5)));
+        assert_eq!(local.direction, Vec3::new(0.0, 0.0, -0.5));
+        assert_eq!(local.at(2.0), Vec3::new(0.0, 0.0, 1.5));
+        assert_eq!(local.at(2.0), ray.at(2.0) * 0.5);
+    }
+}
@@ -1,4 +1,4 @@
 mod system;
-pub use system::RenderSystem;
+pub use system::{RenderSystem, Texture};
 
 mod shaders;
@@ -13,6 +13,28 @@ pub(super) mod deferred_vert {
     }
 }
 
+pub(super) mod deferred_instanced_vert {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/render_system/shaders/deferred_instanced.vert",
+    }
+}
+
+pub(super) mod deferred_textured_frag {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/render_system/shaders/deferred_textured.frag"
+    }
+}
+
+pub(super) mod deferred_textured_vert {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/render_system/shaders/deferred_textured.vert",
+        types_meta: { #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)] },
+    }
+}
+
 pub(super) mod directional_frag {
     vulkano_shaders::shader! {
         ty: "fragment",
@@ -57,6 +79,7 @@ pub(super) mod light_obj_vert {
 pub(super) mod light_obj_frag {
     vulkano_shaders::shader! {
         ty: "fragment",
-        path: "src/render_system/shaders/light_obj.frag"
+        path: "src/render_system/shaders/light_obj.frag",
+        types_meta: { #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)] },
     }
 }
@@ -8,5 +8,7 @@ layout(location = 1) out vec3 f_normal;
 
 void main() {
     f_color = vec4(in_color, 1.0);
-    f_normal = in_normal;
+    // Back faces are only drawn for double-sided models, and face away
+    // from their normals.
+    f_normal = gl_FrontFacing ? in_normal : -in_normal;
 }
\ No newline at end of file
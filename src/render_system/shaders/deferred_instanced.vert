@@ -0,0 +1,32 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 color;
+
+// Per instance: the columns of the model and normal matrices, and a color
+// that replaces the vertex color by its alpha.
+layout(location = 3) in vec4 model_x;
+layout(location = 4) in vec4 model_y;
+layout(location = 5) in vec4 model_z;
+layout(location = 6) in vec4 model_w;
+layout(location = 7) in vec3 normals_x;
+layout(location = 8) in vec3 normals_y;
+layout(location = 9) in vec3 normals_z;
+layout(location = 10) in vec4 instance_color;
+
+layout(location = 0) out vec3 out_color;
+layout(location = 1) out vec3 out_normal;
+
+layout(set = 0, binding = 0) uniform VpData {
+    mat4 view;
+    mat4 projection;
+} vp_uniforms;
+
+void main() {
+    mat4 model = mat4(model_x, model_y, model_z, model_w);
+    mat3 normals = mat3(normals_x, normals_y, normals_z);
+    gl_Position = vp_uniforms.projection * vp_uniforms.view * model * vec4(position, 1.0);
+    out_color = mix(color, instance_color.rgb, instance_color.a);
+    out_normal = normals * normal;
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec3 in_normal;
+
+layout(set = 2, binding = 0) uniform sampler2D u_texture;
+
+layout(location = 0) out vec4 f_color;
+layout(location = 1) out vec3 f_normal;
+
+void main() {
+    f_color = vec4(texture(u_texture, in_uv).rgb, 1.0);
+    // Back faces are only drawn for double-sided models, and face away
+    // from their normals.
+    f_normal = gl_FrontFacing ? in_normal : -in_normal;
+}
\ No newline at end of file
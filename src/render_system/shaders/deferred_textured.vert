@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 uv;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec3 out_normal;
+
+layout(set = 0, binding = 0) uniform VpData {
+    mat4 view;
+    mat4 projection;
+} vp_uniforms;
+
+layout(set = 1, binding = 0) uniform ModelData {
+    mat4 model;
+    mat4 normals;
+} model;
+
+void main() {
+    gl_Position = vp_uniforms.projection * vp_uniforms.view * model.model * vec4(position, 1.0);
+    out_uv = uv;
+    out_normal = mat3(model.normals) * normal;
+}
\ No newline at end of file
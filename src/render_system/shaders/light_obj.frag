@@ -1,8 +1,12 @@
 #version 450
 layout(location = 0) in vec3 in_color;
 
+layout(set = 2, binding = 0) uniform LightObjectData {
+    vec3 color;
+} light;
+
 layout(location = 0) out vec4 f_color;
 
 void main() {
-    f_color = vec4(in_color, 1.0);
+    f_color = vec4(in_color * light.color, 1.0);
 }
\ No newline at end of file
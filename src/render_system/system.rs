@@ -1,22 +1,27 @@
 #![allow(clippy::get_first)]
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 
 use vulkano::{
     buffer::{
-        cpu_pool::CpuBufferPoolSubbuffer, BufferUsage, CpuAccessibleBuffer, CpuBufferPool,
-        TypedBufferAccess,
+        cpu_pool::CpuBufferPoolSubbuffer, BufferAccess, BufferUsage, CpuAccessibleBuffer,
+        CpuBufferPool, DeviceLocalBuffer, TypedBufferAccess,
     },
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents,
+        PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract, RenderPassBeginInfo,
+        SubpassContents,
     },
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
     },
     device::{Device, Queue},
     format::Format,
-    image::{view::ImageView, AttachmentImage, ImageAccess, SwapchainImage},
+    image::{
+        view::ImageView, AttachmentImage, ImageAccess, ImageDimensions, ImmutableImage,
+        MipmapsCount, SwapchainImage,
+    },
     instance::Instance,
     memory::allocator::StandardMemoryAllocator,
     pipeline::{
@@ -31,6 +36,7 @@ use vulkano::{
         GraphicsPipeline, Pipeline, PipelineBindPoint,
     },
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+    sampler::{Sampler, SamplerCreateInfo},
     swapchain::{AcquireError, Surface, Swapchain, SwapchainAcquireFuture, SwapchainPresentInfo},
     sync::{FlushError, GpuFuture},
 };
@@ -40,7 +46,125 @@ use vulkano_win::VkSurfaceBuild;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use super::shaders;
-use crate::{light, obj_loader};
+use crate::{light, obj_loader, transform::Transform};
+
+/// An image uploaded with [`RenderSystem::create_texture`], together with the
+/// sampler used to read it.
+#[derive(Clone)]
+pub struct Texture {
+    view: Arc<ImageView<ImmutableImage>>,
+    sampler: Arc<Sampler>,
+}
+
+// The device-local buffers of one mesh, uploaded on first use and dropped
+// together with the mesh.
+struct GpuMesh {
+    source: MeshVersion,
+    indices: IndexBuffer,
+    vertices: HashMap<VertexKind, Arc<dyn BufferAccess>>,
+}
+
+// The mesh and the `Mesh::generation` of it that buffers were uploaded from
+struct MeshVersion {
+    mesh: Weak<obj_loader::Mesh>,
+    generation: u64,
+}
+
+impl MeshVersion {
+    fn of(mesh: &Arc<obj_loader::Mesh>) -> MeshVersion {
+        MeshVersion {
+            mesh: Arc::downgrade(mesh),
+            generation: mesh.generation(),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.mesh.strong_count() > 0
+    }
+
+    // Whether buffers uploaded from this version can be drawn for `mesh`
+    fn matches(&self, mesh: &Arc<obj_loader::Mesh>) -> bool {
+        Weak::ptr_eq(&self.mesh, &Arc::downgrade(mesh)) && self.generation == mesh.generation()
+    }
+}
+
+#[derive(Clone)]
+enum IndexBuffer {
+    U16(Arc<DeviceLocalBuffer<[u16]>>),
+    U32(Arc<DeviceLocalBuffer<[u32]>>),
+}
+
+// The vertex formats a mesh can be drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum VertexKind {
+    Normal,
+    Textured,
+    Colored,
+}
+
+// The per-instance attributes of `deferred_instanced.vert`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Zeroable, bytemuck::Pod)]
+struct InstanceData {
+    model_x: [f32; 4],
+    model_y: [f32; 4],
+    model_z: [f32; 4],
+    model_w: [f32; 4],
+    normals_x: [f32; 3],
+    normals_y: [f32; 3],
+    normals_z: [f32; 3],
+    // An alpha of 0 keeps the vertex colors
+    instance_color: [f32; 4],
+}
+vulkano::impl_vertex!(
+    InstanceData,
+    model_x,
+    model_y,
+    model_z,
+    model_w,
+    normals_x,
+    normals_y,
+    normals_z,
+    instance_color
+);
+
+impl InstanceData {
+    fn new(transform: &Transform, color: Option<[f32; 3]>) -> InstanceData {
+        let [model_x, model_y, model_z, model_w] = transform.matrix().to_cols_array_2d();
+        let [normals_x, normals_y, normals_z] =
+            glam::Mat3::from_mat4(transform.normal_matrix()).to_cols_array_2d();
+        let instance_color = match color {
+            Some([r, g, b]) => [r, g, b, 1.0],
+            None => [0.0; 4],
+        };
+        InstanceData {
+            model_x,
+            model_y,
+            model_z,
+            model_w,
+            normals_x,
+            normals_y,
+            normals_z,
+            instance_color,
+        }
+    }
+
+    // One instance for each transform, painted in the color at its index.
+    fn colored<'a>(
+        transforms: &'a [Transform],
+        colors: &'a [[f32; 3]],
+    ) -> impl ExactSizeIterator<Item = InstanceData> + 'a {
+        assert_eq!(
+            transforms.len(),
+            colors.len(),
+            "every instance needs a color"
+        );
+        transforms
+            .iter()
+            .zip(colors)
+            .map(|(t, color)| InstanceData::new(t, Some(*color)))
+    }
+}
 
 #[derive(Debug, Clone)]
 enum RenderStage {
@@ -68,8 +192,16 @@ pub struct RenderSystem {
     model_uniform_buffer_pool: CpuBufferPool<shaders::deferred_vert::ty::ModelData>,
     directional_uniform_buffer_pool:
         CpuBufferPool<shaders::directional_frag::ty::DirectionalLightData>,
+    instance_buffer_pool: CpuBufferPool<InstanceData>,
+    light_obj_uniform_buffer_pool: CpuBufferPool<shaders::light_obj_frag::ty::LightObjectData>,
     render_pass: Arc<RenderPass>,
     deferred_pipeline: Arc<GraphicsPipeline>,
+    deferred_textured_pipeline: Arc<GraphicsPipeline>,
+    // Variants of the deferred pipelines without culling, for models with
+    // `obj_loader::CullMode::None`
+    double_sided_pipeline: Arc<GraphicsPipeline>,
+    double_sided_textured_pipeline: Arc<GraphicsPipeline>,
+    instanced_pipeline: Arc<GraphicsPipeline>,
     directional_pipeline: Arc<GraphicsPipeline>,
     ambient_pipeline: Arc<GraphicsPipeline>,
     light_obj_pipeline: Arc<GraphicsPipeline>,
@@ -81,6 +213,14 @@ pub struct RenderSystem {
     vp: crate::mvp::VP,
     vp_buffer: Arc<CpuAccessibleBuffer<shaders::deferred_vert::ty::VpData>>,
     vp_set: Arc<PersistentDescriptorSet>,
+    meshes: obj_loader::MeshRegistry,
+    // Keyed by `Mesh::id`
+    gpu_meshes: HashMap<u64, GpuMesh>,
+    // Copies from staging buffers into the buffers of `gpu_meshes`, executed
+    // before the commands of the next finished frame
+    uploads: Option<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>>,
+    // The keys of `gpu_meshes` with buffers filled by `uploads`
+    uploaded_meshes: Vec<u64>,
     render_stage: RenderStage,
     commands: Option<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>>,
     image_index: u32,
@@ -105,6 +245,10 @@ impl RenderSystem {
 
         let deferred_vert = shaders::deferred_vert::load(device.clone()).unwrap();
         let deferred_frag = shaders::deferred_frag::load(device.clone()).unwrap();
+        let deferred_instanced_vert =
+            shaders::deferred_instanced_vert::load(device.clone()).unwrap();
+        let deferred_textured_vert = shaders::deferred_textured_vert::load(device.clone()).unwrap();
+        let deferred_textured_frag = shaders::deferred_textured_frag::load(device.clone()).unwrap();
         let directional_vert = shaders::directional_vert::load(device.clone()).unwrap();
         let directional_frag = shaders::directional_frag::load(device.clone()).unwrap();
         let ambient_vert = shaders::ambient_vert::load(device.clone()).unwrap();
@@ -117,6 +261,11 @@ impl RenderSystem {
         let directional_uniform_buffer_pool: CpuBufferPool<
             shaders::directional_frag::ty::DirectionalLightData,
         > = CpuBufferPool::uniform_buffer(memory_allocator.clone());
+        let instance_buffer_pool: CpuBufferPool<InstanceData> =
+            CpuBufferPool::vertex_buffer(memory_allocator.clone());
+        let light_obj_uniform_buffer_pool: CpuBufferPool<
+            shaders::light_obj_frag::ty::LightObjectData,
+        > = CpuBufferPool::uniform_buffer(memory_allocator.clone());
 
         let render_pass = vulkano::ordered_passes_renderpass!(device.clone(),
             attachments: {
@@ -175,6 +324,62 @@ impl RenderSystem {
             .build(device.clone())
             .expect("Failed to create pipeline");
 
+        let deferred_textured_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(
+                BuffersDefinition::new().vertex::<obj_loader::TexturedNormalVertex>(),
+            )
+            .vertex_shader(deferred_textured_vert.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(deferred_textured_frag.entry_point("main").unwrap(), ())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
+            .render_pass(deferred_pass.clone())
+            .build(device.clone())
+            .expect("Failed to create pipeline");
+
+        let double_sided_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<obj_loader::NormalVertex>())
+            .vertex_shader(deferred_vert.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(deferred_frag.entry_point("main").unwrap(), ())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .rasterization_state(RasterizationState::new().cull_mode(CullMode::None))
+            .render_pass(deferred_pass.clone())
+            .build(device.clone())
+            .expect("Failed to create pipeline");
+
+        let double_sided_textured_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(
+                BuffersDefinition::new().vertex::<obj_loader::TexturedNormalVertex>(),
+            )
+            .vertex_shader(deferred_textured_vert.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(deferred_textured_frag.entry_point("main").unwrap(), ())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .rasterization_state(RasterizationState::new().cull_mode(CullMode::None))
+            .render_pass(deferred_pass.clone())
+            .build(device.clone())
+            .expect("Failed to create pipeline");
+
+        let instanced_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(
+                BuffersDefinition::new()
+                    .vertex::<obj_loader::NormalVertex>()
+                    .instance::<InstanceData>(),
+            )
+            .vertex_shader(deferred_instanced_vert.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(deferred_frag.entry_point("main").unwrap(), ())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
+            .render_pass(deferred_pass.clone())
+            .build(device.clone())
+            .expect("Failed to create pipeline");
+
         let directional_pipeline = GraphicsPipeline::start()
             .vertex_input_state(BuffersDefinition::new().vertex::<obj_loader::DummyVertex>())
             .vertex_shader(directional_vert.entry_point("main").unwrap(), ())
@@ -281,8 +486,14 @@ impl RenderSystem {
             command_buffer_allocator,
             model_uniform_buffer_pool,
             directional_uniform_buffer_pool,
+            instance_buffer_pool,
+            light_obj_uniform_buffer_pool,
             render_pass,
             deferred_pipeline,
+            deferred_textured_pipeline,
+            double_sided_pipeline,
+            double_sided_textured_pipeline,
+            instanced_pipeline,
             directional_pipeline,
             ambient_pipeline,
             light_obj_pipeline,
@@ -294,6 +505,10 @@ impl RenderSystem {
             vp,
             vp_buffer,
             vp_set,
+            meshes: obj_loader::MeshRegistry::new(),
+            gpu_meshes: HashMap::new(),
+            uploads: None,
+            uploaded_meshes: Vec::new(),
             render_stage: RenderStage::Stopped,
             commands: None,
             image_index: 0,
@@ -301,6 +516,14 @@ impl RenderSystem {
         }
     }
 
+    /// The world space ray through a cursor position in pixels, for
+    /// picking with [`obj_loader::Model::raycast`].
+    pub fn screen_ray(&self, position: glam::Vec2) -> crate::ray::Ray {
+        let extent: [u32; 2] = crate::setup::surface_extent(&self.surface).into();
+        self.vp
+            .screen_ray(position, glam::UVec2::from(extent).as_vec2())
+    }
+
     pub fn set_view(&mut self, view: &glam::Mat4) {
         self.vp.view = *view;
         self.vp_buffer = create_uniform_buffer(&self.vp, self.memory_allocator.clone());
@@ -399,58 +622,123 @@ impl RenderSystem {
             }
         }
 
-        let model_subbuffer = {
-            let (model_mat, normal_mat) = (model.model_matrix(), model.normal_matrix());
-
-            let uniform_data = shaders::deferred_vert::ty::ModelData {
-                model: model_mat.to_cols_array_2d(),
-                normals: normal_mat.to_cols_array_2d(),
-            };
-
-            self.model_uniform_buffer_pool
-                .from_data(uniform_data)
-                .unwrap()
+        let pipeline = match model.cull_mode() {
+            obj_loader::CullMode::Back => self.deferred_pipeline.clone(),
+            obj_loader::CullMode::None => self.double_sided_pipeline.clone(),
         };
+        self.draw_sub_meshes(model, pipeline, VertexKind::Normal, Vec::new());
+    }
 
-        let model_layout = self
-            .deferred_pipeline
+    /// Same as [`RenderSystem::render_model`], with the model colored by
+    /// `texture` through its texture coordinates.
+    pub fn render_textured_model(&mut self, model: &mut obj_loader::Model, texture: &Texture) {
+        match self.render_stage {
+            RenderStage::Deferred => {}
+            RenderStage::NeedsRedraw => {
+                self.recreate_swapchain();
+                self.render_stage = RenderStage::Stopped;
+                self.commands = None;
+                return;
+            }
+            _ => {
+                self.render_stage = RenderStage::Stopped;
+                self.commands = None;
+                return;
+            }
+        }
+
+        let texture_layout = self
+            .deferred_textured_pipeline
             .layout()
             .set_layouts()
-            .get(1)
+            .get(2)
             .unwrap();
-        let model_set = PersistentDescriptorSet::new(
+        let texture_set = PersistentDescriptorSet::new(
             &self.descriptor_set_allocator,
-            model_layout.clone(),
-            [WriteDescriptorSet::buffer(0, model_subbuffer.clone())],
+            texture_layout.clone(),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                texture.view.clone(),
+                texture.sampler.clone(),
+            )],
         )
         .unwrap();
 
-        let vertex_buffer = CpuAccessibleBuffer::from_iter(
-            &self.memory_allocator,
-            BufferUsage {
-                vertex_buffer: true,
-                ..BufferUsage::empty()
-            },
-            false,
-            model.data().iter().cloned(),
+        let pipeline = match model.cull_mode() {
+            obj_loader::CullMode::Back => self.deferred_textured_pipeline.clone(),
+            obj_loader::CullMode::None => self.double_sided_textured_pipeline.clone(),
+        };
+        self.draw_sub_meshes(model, pipeline, VertexKind::Textured, vec![texture_set]);
+    }
+
+    /// Draws a copy of `mesh` at each of `transforms` with a single draw
+    /// call. The whole mesh is drawn for every copy, without the transforms
+    /// and visibility of its sub-meshes, and with its faces culled like
+    /// [`obj_loader::CullMode::Back`].
+    pub fn render_instanced(&mut self, mesh: &Arc<obj_loader::Mesh>, transforms: &[Transform]) {
+        let instances = transforms.iter().map(|t| InstanceData::new(t, None));
+        self.draw_instanced(mesh, instances);
+    }
+
+    /// Same as [`RenderSystem::render_instanced`], with each copy painted
+    /// in the color at the same index of `colors` instead of its vertex
+    /// colors.
+    ///
+    /// # Panics
+    ///
+    /// If `colors` and `transforms` have different lengths.
+    pub fn render_instanced_colored(
+        &mut self,
+        mesh: &Arc<obj_loader::Mesh>,
+        transforms: &[Transform],
+        colors: &[[f32; 3]],
+    ) {
+        let instances = InstanceData::colored(transforms, colors);
+        self.draw_instanced(mesh, instances);
+    }
+
+    /// Uploads an image made of `width * height` RGBA pixels in sRGB color
+    /// space, for use with [`RenderSystem::render_textured_model`].
+    pub fn create_texture(&self, rgba: &[u8], width: u32, height: u32) -> Texture {
+        let mut uploads = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
         )
         .unwrap();
 
-        let view_port = self.view_port_from_surface();
-        self.commands
-            .as_mut()
+        let image = ImmutableImage::from_iter(
+            &*self.memory_allocator,
+            rgba.iter().cloned(),
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8G8B8A8_SRGB,
+            &mut uploads,
+        )
+        .expect("Failed to create texture image");
+
+        uploads
+            .build()
             .unwrap()
-            .set_viewport(0, [view_port])
-            .bind_pipeline_graphics(self.deferred_pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.deferred_pipeline.layout().clone(),
-                0,
-                (self.vp_set.clone(), model_set.clone()),
-            )
-            .bind_vertex_buffers(0, vertex_buffer.clone())
-            .draw(vertex_buffer.len() as u32, 1, 0, 0)
+            .execute(self.queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
             .unwrap();
+
+        Texture {
+            view: ImageView::new_default(image).expect("Failed to create texture image view"),
+            sampler: Sampler::new(
+                self.device.clone(),
+                SamplerCreateInfo::simple_repeat_linear_no_mipmap(),
+            )
+            .expect("Failed to create sampler"),
+        }
     }
 
     pub fn render_directional(&mut self, directional_light: &light::DirectionalLight) {
@@ -578,63 +866,46 @@ impl RenderSystem {
             }
         }
 
-        let mut model = obj_loader::Model::builder("models/sphere.obj")
-            .color(directional_light.color)
-            .uniform_scale_factor(0.2)
-            .build();
-
+        // A white sphere, shared by every light through the registry and
+        // tinted with the color of the light
+        let mut model = self
+            .meshes
+            .model(
+                obj_loader::Model::primitive(obj_loader::Primitive::UvSphere {
+                    segments: 32,
+                    rings: 16,
+                })
+                .color([1.0, 1.0, 1.0])
+                .uniform_scale_factor(0.2),
+            )
+            .expect("Failed to load light object model");
         model.translate(directional_light.get_position());
 
-        let model_subbuffer = {
-            let (model_mat, normal_mat) = (model.model_matrix(), model.normal_matrix());
-
-            let uniform_data = shaders::deferred_vert::ty::ModelData {
-                model: model_mat.to_cols_array_2d(),
-                normals: normal_mat.to_cols_array_2d(),
-            };
-
-            self.model_uniform_buffer_pool
-                .from_data(uniform_data)
-                .unwrap()
-        };
-
-        let model_layout = self
+        let color_subbuffer = self
+            .light_obj_uniform_buffer_pool
+            .from_data(shaders::light_obj_frag::ty::LightObjectData {
+                color: directional_light.color,
+            })
+            .unwrap();
+        let color_layout = self
             .light_obj_pipeline
             .layout()
             .set_layouts()
-            .get(1)
+            .get(2)
             .unwrap();
-        let model_set = PersistentDescriptorSet::new(
+        let color_set = PersistentDescriptorSet::new(
             &self.descriptor_set_allocator,
-            model_layout.clone(),
-            [WriteDescriptorSet::buffer(0, model_subbuffer.clone())],
-        )
-        .unwrap();
-
-        let vertex_buffer = CpuAccessibleBuffer::from_iter(
-            &self.memory_allocator,
-            BufferUsage {
-                vertex_buffer: true,
-                ..BufferUsage::empty()
-            },
-            false,
-            model.color_data().iter().cloned(),
+            color_layout.clone(),
+            [WriteDescriptorSet::buffer(0, color_subbuffer)],
         )
         .unwrap();
 
-        self.commands
-            .as_mut()
-            .unwrap()
-            .bind_pipeline_graphics(self.light_obj_pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.light_obj_pipeline.layout().clone(),
-                0,
-                (self.vp_set.clone(), model_set.clone()),
-            )
-            .bind_vertex_buffers(0, vertex_buffer.clone())
-            .draw(vertex_buffer.len() as u32, 1, 0, 0)
-            .unwrap();
+        self.draw_sub_meshes(
+            &model,
+            self.light_obj_pipeline.clone(),
+            VertexKind::Colored,
+            vec![color_set],
+        );
     }
 
     pub fn finish_frame(&mut self, previous_frame_end: &mut Option<Box<dyn GpuFuture>>) {
@@ -665,10 +936,18 @@ impl RenderSystem {
 
         std::mem::swap(&mut local_future, previous_frame_end);
 
+        // Meshes drawn for the first time are copied to the GPU before the
+        // frame that draws them.
+        let mut local_future = local_future.take().unwrap().join(af).boxed();
+        let uploaded_meshes = std::mem::take(&mut self.uploaded_meshes);
+        if let Some(uploads) = self.uploads.take() {
+            local_future = local_future
+                .then_execute(self.queue.clone(), uploads.build().unwrap())
+                .unwrap()
+                .boxed();
+        }
+
         let future = local_future
-            .take()
-            .unwrap()
-            .join(af)
             .then_execute(self.queue.clone(), command_buffer)
             .unwrap()
             .then_swapchain_present(
@@ -685,17 +964,24 @@ impl RenderSystem {
                 *previous_frame_end = Some(Box::new(future) as Box<_>);
             }
             Err(FlushError::OutOfDate) => {
+                self.discard_gpu_meshes(&uploaded_meshes);
                 self.recreate_swapchain();
                 *previous_frame_end =
                     Some(Box::new(vulkano::sync::now(self.device.clone())) as Box<_>);
             }
             Err(e) => {
                 println!("Failed to flush future: {:?}", e);
+                self.discard_gpu_meshes(&uploaded_meshes);
                 *previous_frame_end =
                     Some(Box::new(vulkano::sync::now(self.device.clone())) as Box<_>);
             }
         }
 
+        // Buffers of meshes that were dropped since they were drawn are no
+        // longer needed.
+        self.gpu_meshes
+            .retain(|_, gpu_mesh| gpu_mesh.source.is_alive());
+
         self.commands = None;
         self.render_stage = RenderStage::Stopped;
     }
@@ -752,6 +1038,197 @@ impl RenderSystem {
         self.device.clone()
     }
 
+    /// Builds a model whose mesh is loaded only once, no matter how many
+    /// models are made from it, see [`obj_loader::MeshRegistry`]. The
+    /// buffers of the mesh are shared by all of them as well.
+    pub fn load_model(
+        &mut self,
+        builder: obj_loader::ModelBuilder,
+    ) -> Result<obj_loader::Model, obj_loader::ObjError> {
+        self.meshes.model(builder)
+    }
+
+    // The buffers of `mesh`, with the vertex buffer in the format of `kind`
+    // created if this is the first time it is drawn that way.
+    // An edited mesh is uploaded again as a whole.
+    fn gpu_mesh(&mut self, mesh: &Arc<obj_loader::Mesh>, kind: VertexKind) -> &GpuMesh {
+        let key = mesh.id();
+        let uploaded = self.gpu_meshes.get(&key).is_some_and(|gpu_mesh| {
+            gpu_mesh.source.matches(mesh) && gpu_mesh.vertices.contains_key(&kind)
+        });
+        if uploaded {
+            return &self.gpu_meshes[&key];
+        }
+
+        let allocator = &self.memory_allocator;
+        let uploads = self.uploads.get_or_insert_with(|| {
+            AutoCommandBufferBuilder::primary(
+                &self.command_buffer_allocator,
+                self.queue.queue_family_index(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap()
+        });
+        if self
+            .gpu_meshes
+            .get(&key)
+            .is_some_and(|gpu_mesh| !gpu_mesh.source.matches(mesh))
+        {
+            self.gpu_meshes.remove(&key);
+        }
+        self.uploaded_meshes.push(key);
+        let gpu_mesh = self.gpu_meshes.entry(key).or_insert_with(|| GpuMesh {
+            source: MeshVersion::of(mesh),
+            indices: create_index_buffer(&mesh.geometry().indices, allocator, uploads),
+            vertices: HashMap::new(),
+        });
+        gpu_mesh.vertices.entry(kind).or_insert_with(|| {
+            let geometry = mesh.geometry();
+            match kind {
+                VertexKind::Normal => {
+                    create_vertex_buffer(geometry.vertices.iter().cloned(), allocator, uploads)
+                }
+                VertexKind::Textured => {
+                    create_vertex_buffer(mesh.textured_vertices(), allocator, uploads)
+                }
+                VertexKind::Colored => {
+                    create_vertex_buffer(mesh.colored_vertices(), allocator, uploads)
+                }
+            }
+        });
+        gpu_mesh
+    }
+
+    // Forgets the buffers of meshes whose uploads were never submitted, so
+    // they are uploaded again the next time they are drawn.
+    fn discard_gpu_meshes(&mut self, keys: &[u64]) {
+        for key in keys {
+            self.gpu_meshes.remove(key);
+        }
+    }
+
+    // Draws every visible sub-mesh of `model` with its own model matrices.
+    // The view-projection set and the model set are bound to sets 0 and 1,
+    // `extra_sets` to the sets after them.
+    fn draw_sub_meshes(
+        &mut self,
+        model: &obj_loader::Model,
+        pipeline: Arc<GraphicsPipeline>,
+        kind: VertexKind,
+        extra_sets: Vec<Arc<PersistentDescriptorSet>>,
+    ) {
+        if model.mesh().indices.is_empty() {
+            return;
+        }
+        let view_port = self.view_port_from_surface();
+        let gpu_mesh = self.gpu_mesh(model.shared_mesh(), kind);
+        let vertex_buffer = gpu_mesh.vertices[&kind].clone();
+        let index_buffer = gpu_mesh.indices.clone();
+        let commands = self.commands.as_mut().unwrap();
+        commands
+            .set_viewport(0, [view_port])
+            .bind_pipeline_graphics(pipeline.clone())
+            .bind_vertex_buffers(0, vertex_buffer);
+        match index_buffer {
+            IndexBuffer::U16(indices) => commands.bind_index_buffer(indices),
+            IndexBuffer::U32(indices) => commands.bind_index_buffer(indices),
+        };
+
+        let model_layout = pipeline.layout().set_layouts().get(1).unwrap();
+
+        for sub_mesh in model.sub_meshes().iter().filter(|s| s.visible) {
+            let model_subbuffer = {
+                let (model_mat, normal_mat) = model.sub_mesh_matrices(sub_mesh);
+
+                let uniform_data = shaders::deferred_vert::ty::ModelData {
+                    model: model_mat.to_cols_array_2d(),
+                    normals: normal_mat.to_cols_array_2d(),
+                };
+
+                self.model_uniform_buffer_pool
+                    .from_data(uniform_data)
+                    .unwrap()
+            };
+
+            let model_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                model_layout.clone(),
+                [WriteDescriptorSet::buffer(0, model_subbuffer.clone())],
+            )
+            .unwrap();
+
+            let mut sets = vec![self.vp_set.clone(), model_set];
+            sets.extend(extra_sets.iter().cloned());
+
+            commands
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    0,
+                    sets,
+                )
+                .draw_indexed(
+                    sub_mesh.indices.len() as u32,
+                    1,
+                    sub_mesh.indices.start,
+                    0,
+                    0,
+                )
+                .unwrap();
+        }
+    }
+
+    fn draw_instanced(
+        &mut self,
+        mesh: &Arc<obj_loader::Mesh>,
+        instances: impl ExactSizeIterator<Item = InstanceData>,
+    ) {
+        match self.render_stage {
+            RenderStage::Deferred => {}
+            RenderStage::NeedsRedraw => {
+                self.recreate_swapchain();
+                self.render_stage = RenderStage::Stopped;
+                self.commands = None;
+                return;
+            }
+            _ => {
+                self.render_stage = RenderStage::Stopped;
+                self.commands = None;
+                return;
+            }
+        }
+
+        let instance_count = instances.len() as u32;
+        let index_count = mesh.geometry().indices.len() as u32;
+        if instance_count == 0 || index_count == 0 {
+            return;
+        }
+        let instance_buffer = self.instance_buffer_pool.from_iter(instances).unwrap();
+        let view_port = self.view_port_from_surface();
+        let gpu_mesh = self.gpu_mesh(mesh, VertexKind::Normal);
+        let vertex_buffer = gpu_mesh.vertices[&VertexKind::Normal].clone();
+        let index_buffer = gpu_mesh.indices.clone();
+        let pipeline = self.instanced_pipeline.clone();
+        let commands = self.commands.as_mut().unwrap();
+        commands
+            .set_viewport(0, [view_port])
+            .bind_pipeline_graphics(pipeline.clone())
+            .bind_vertex_buffers(0, (vertex_buffer, instance_buffer));
+        match index_buffer {
+            IndexBuffer::U16(indices) => commands.bind_index_buffer(indices),
+            IndexBuffer::U32(indices) => commands.bind_index_buffer(indices),
+        };
+        commands
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                self.vp_set.clone(),
+            )
+            .draw_indexed(index_count, instance_count, 0, 0, 0)
+            .unwrap();
+    }
+
     fn view_port_from_surface(&self) -> Viewport {
         Viewport {
             origin: [0.0, 0.0],
@@ -838,6 +1315,46 @@ fn create_framebuffer(
     (framebuffers, color_buffer.clone(), normal_buffer.clone())
 }
 
+// Records the copy from a staging buffer into a new device-local buffer in
+// `uploads`.
+fn create_vertex_buffer<V: bytemuck::Pod + Send + Sync>(
+    vertices: impl IntoIterator<Item = V, IntoIter = impl ExactSizeIterator<Item = V>>,
+    allocator: &StandardMemoryAllocator,
+    uploads: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) -> Arc<dyn BufferAccess> {
+    DeviceLocalBuffer::from_iter(
+        allocator,
+        vertices,
+        BufferUsage {
+            vertex_buffer: true,
+            ..BufferUsage::empty()
+        },
+        uploads,
+    )
+    .expect("Failed to create vertex buffer")
+}
+
+fn create_index_buffer(
+    indices: &obj_loader::Indices,
+    allocator: &StandardMemoryAllocator,
+    uploads: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) -> IndexBuffer {
+    let usage = BufferUsage {
+        index_buffer: true,
+        ..BufferUsage::empty()
+    };
+    match indices {
+        obj_loader::Indices::U16(indices) => IndexBuffer::U16(
+            DeviceLocalBuffer::from_iter(allocator, indices.iter().cloned(), usage, uploads)
+                .expect("Failed to create index buffer"),
+        ),
+        obj_loader::Indices::U32(indices) => IndexBuffer::U32(
+            DeviceLocalBuffer::from_iter(allocator, indices.iter().cloned(), usage, uploads)
+                .expect("Failed to create index buffer"),
+        ),
+    }
+}
+
 pub fn create_uniform_buffer(
     vp: &crate::mvp::VP,
     memory_allocator: Arc<StandardMemoryAllocator>,
@@ -856,3 +1373,100 @@ pub fn create_uniform_buffer(
     )
     .expect("Failed to create VP buffer")
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat3, Quat, Vec3};
+
+    use super::*;
+
+    fn sphere() -> Arc<obj_loader::Mesh> {
+        let shape = obj_loader::Primitive::UvSphere {
+            segments: 8,
+            rings: 4,
+        };
+        Arc::new(shape.mesh([1.0, 1.0, 1.0], false))
+    }
+
+    #[test]
+    fn mesh_versions_match_their_own_mesh() {
+        let mesh = sphere();
+        let version = MeshVersion::of(&mesh);
+        assert!(version.is_alive());
+        assert!(version.matches(&mesh));
+        assert!(!version.matches(&sphere()));
+    }
+
+    #[test]
+    fn mesh_versions_reject_a_mesh_allocated_after_theirs_was_dropped() {
+        let version = MeshVersion::of(&sphere());
+        assert!(!version.is_alive());
+        let replacement = sphere();
+        assert_eq!(replacement.generation(), version.generation);
+        assert!(!version.matches(&replacement));
+    }
+
+    #[test]
+    fn buffers_are_keyed_by_ids_no_other_mesh_has() {
+        let mesh = sphere();
+        let copy = obj_loader::Mesh::clone(&mesh);
+        assert_ne!(copy.id(), mesh.id());
+        let id = mesh.id();
+        drop(mesh);
+        assert_ne!(sphere().id(), id);
+    }
+
+    #[test]
+    fn mesh_versions_reject_edited_meshes() {
+        let mesh = sphere();
+        let version = MeshVersion::of(&mesh);
+        let mut model = obj_loader::Model::from_mesh(mesh.clone());
+        model.edit_mesh(|_| {});
+        assert!(version.matches(&mesh));
+        assert!(!version.matches(model.shared_mesh()));
+    }
+
+    #[test]
+    fn instances_hold_the_model_and_normal_matrices_column_by_column() {
+        let transform = Transform {
+            translation: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(0.5),
+            scale: Vec3::new(1.0, 2.0, 4.0),
+        };
+        let instance = InstanceData::new(&transform, None);
+        // The attributes in the order `deferred_instanced.vert` reads them
+        let floats: &[f32] = bytemuck::cast_slice(std::slice::from_ref(&instance));
+        assert_eq!(floats.len(), 16 + 9 + 4);
+        assert_eq!(floats[..16], transform.matrix().to_cols_array());
+        let normal = Mat3::from_mat4(transform.matrix()).inverse().transpose();
+        for (stored, expected) in floats[16..25].iter().zip(normal.to_cols_array()) {
+            assert!((stored - expected).abs() < 1e-5);
+        }
+        assert_eq!(floats[12..15], [1.0, 2.0, 3.0]);
+        // No color keeps the vertex colors
+        assert_eq!(floats[25..], [0.0; 4]);
+
+        let colored = InstanceData::new(&transform, Some([0.2, 0.4, 0.6]));
+        assert_eq!(colored.instance_color, [0.2, 0.4, 0.6, 1.0]);
+        assert_eq!(colored.model_w, instance.model_w);
+    }
+
+    #[test]
+    fn colored_instances_pair_transforms_with_colors() {
+        let transforms = [1.0, 2.0, 3.0].map(|x| Transform::from_translation(Vec3::X * x));
+        let colors = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        let instances: Vec<InstanceData> = InstanceData::colored(&transforms, &colors).collect();
+        assert_eq!(instances.len(), 3);
+        for ((instance, transform), color) in instances.iter().zip(&transforms).zip(colors) {
+            assert_eq!(instance.model_w[0], transform.translation.x);
+            assert_eq!(instance.instance_color[..3], color);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "every instance needs a color")]
+    fn every_instance_needs_a_color() {
+        let transforms = [Transform::IDENTITY; 2];
+        let _ = InstanceData::colored(&transforms, &[[1.0; 3]]);
+    }
+}
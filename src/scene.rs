@@ -0,0 +1,469 @@
+use std::cell::Cell;
+
+use glam::Mat4;
+
+use crate::light::DirectionalLight;
+use crate::obj_loader::Model;
+use crate::transform::Transform;
+
+/// A handle to a node of a [`Scene`]. Handles of removed nodes stay invalid
+/// even after their slot is reused by a new node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// What a node carries besides its transform.
+// Most attachments are models, so boxing them would only add indirection.
+#[allow(clippy::large_enum_variant)]
+pub enum Attachment {
+    /// A model drawn at the world matrix of the node, with its own
+    /// [`Model::transform`] applied first.
+    Model(Model),
+    /// A light whose position is relative to the node.
+    Light(DirectionalLight),
+}
+
+struct Node {
+    local: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    attachment: Option<Attachment>,
+    // `None` after the node or one of its ancestors moved. A node without a
+    // world matrix never has descendants with one.
+    world: Cell<Option<Mat4>>,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// A hierarchy of nodes, each placed relative to its parent.
+///
+/// World matrices are computed when they are asked for and kept until the
+/// node or one of its ancestors moves, like the matrices of [`Model`].
+/// Methods taking a [`NodeId`] panic if the node was removed, like indexing
+/// a `Vec` out of bounds.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    // Slots of removed nodes, reused by new ones
+    free: Vec<u32>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// Adds an empty node, at the root of the scene if `parent` is `None`.
+    pub fn add(&mut self, parent: Option<NodeId>, local: Transform) -> NodeId {
+        if let Some(parent) = parent {
+            self.node(parent);
+        }
+        let node = Node {
+            local,
+            parent,
+            children: Vec::new(),
+            attachment: None,
+            world: Cell::new(None),
+        };
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Adds a node carrying `model`, without a transform of its own.
+    pub fn add_model(&mut self, parent: Option<NodeId>, model: Model) -> NodeId {
+        let id = self.add(parent, Transform::IDENTITY);
+        self.node_mut(id).attachment = Some(Attachment::Model(model));
+        id
+    }
+
+    /// Adds a node carrying `light`, without a transform of its own.
+    pub fn add_light(&mut self, parent: Option<NodeId>, light: DirectionalLight) -> NodeId {
+        let id = self.add(parent, Transform::IDENTITY);
+        self.node_mut(id).attachment = Some(Attachment::Light(light));
+        id
+    }
+
+    /// Removes `node` together with all of its descendants.
+    pub fn remove(&mut self, node: NodeId) {
+        match self.node(node).parent {
+            Some(parent) => self.node_mut(parent).children.retain(|c| *c != node),
+            None => self.roots.retain(|r| *r != node),
+        }
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index as usize];
+            let removed = slot.node.take().unwrap();
+            slot.generation += 1;
+            self.free.push(id.index);
+            stack.extend(removed.children);
+        }
+    }
+
+    /// Whether `node` is still part of the scene.
+    pub fn contains(&self, node: NodeId) -> bool {
+        self.slots
+            .get(node.index as usize)
+            .is_some_and(|slot| slot.generation == node.generation && slot.node.is_some())
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The nodes without a parent, in the order they were added.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.node(node).parent
+    }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.node(node).children
+    }
+
+    /// Moves `node` with its descendants under `parent`, or to the root of
+    /// the scene if `parent` is `None`. The local transform is kept, so the
+    /// node moves along with its new parent. Returns false without changing
+    /// anything if `parent` is `node` itself or one of its descendants.
+    pub fn set_parent(&mut self, node: NodeId, parent: Option<NodeId>) -> bool {
+        let mut ancestor = parent;
+        while let Some(id) = ancestor {
+            if id == node {
+                return false;
+            }
+            ancestor = self.node(id).parent;
+        }
+        match self.node(node).parent {
+            Some(old) => self.node_mut(old).children.retain(|c| *c != node),
+            None => self.roots.retain(|r| *r != node),
+        }
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(node),
+            None => self.roots.push(node),
+        }
+        self.node_mut(node).parent = parent;
+        self.invalidate(node);
+        true
+    }
+
+    /// The transform of `node` relative to its parent.
+    pub fn local(&self, node: NodeId) -> &Transform {
+        &self.node(node).local
+    }
+
+    pub fn set_local(&mut self, node: NodeId, local: Transform) {
+        *self.local_mut(node) = local;
+    }
+
+    /// Changes the local transform in place.
+    pub fn local_mut(&mut self, node: NodeId) -> &mut Transform {
+        // Nothing can read the world matrices while the transform is
+        // borrowed.
+        self.invalidate(node);
+        &mut self.node_mut(node).local
+    }
+
+    /// The transform of `node` relative to the scene, which is its local
+    /// transform applied after those of all its ancestors.
+    pub fn world_matrix(&self, node: NodeId) -> Mat4 {
+        // Walk up to the first ancestor that still knows its world matrix,
+        // then down again filling in the rest.
+        let mut path = Vec::new();
+        let mut current = Some(node);
+        let mut world = Mat4::IDENTITY;
+        while let Some(id) = current {
+            let node = self.node(id);
+            if let Some(cached) = node.world.get() {
+                world = cached;
+                break;
+            }
+            path.push(node);
+            current = node.parent;
+        }
+        for node in path.into_iter().rev() {
+            world *= node.local.matrix();
+            node.world.set(Some(world));
+        }
+        world
+    }
+
+    pub fn attachment(&self, node: NodeId) -> Option<&Attachment> {
+        self.node(node).attachment.as_ref()
+    }
+
+    pub fn attachment_mut(&mut self, node: NodeId) -> Option<&mut Attachment> {
+        self.node_mut(node).attachment.as_mut()
+    }
+
+    /// Replaces what `node` carries, returning the previous attachment.
+    pub fn set_attachment(
+        &mut self,
+        node: NodeId,
+        attachment: Option<Attachment>,
+    ) -> Option<Attachment> {
+        std::mem::replace(&mut self.node_mut(node).attachment, attachment)
+    }
+
+    pub fn model(&self, node: NodeId) -> Option<&Model> {
+        match self.attachment(node) {
+            Some(Attachment::Model(model)) => Some(model),
+            _ => None,
+        }
+    }
+
+    pub fn model_mut(&mut self, node: NodeId) -> Option<&mut Model> {
+        match self.attachment_mut(node) {
+            Some(Attachment::Model(model)) => Some(model),
+            _ => None,
+        }
+    }
+
+    /// Places the model of every node at the node's world matrix, see
+    /// [`Model::set_parent_matrix`]. Models that didn't move keep their
+    /// cached matrices.
+    pub fn update(&mut self) {
+        for index in 0..self.slots.len() {
+            let slot = &self.slots[index];
+            let id = NodeId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            if !matches!(
+                slot.node.as_ref().and_then(|n| n.attachment.as_ref()),
+                Some(Attachment::Model(_))
+            ) {
+                continue;
+            }
+            let world = self.world_matrix(id);
+            if let Some(model) = self.model_mut(id) {
+                model.set_parent_matrix(world);
+            }
+        }
+    }
+
+    /// Every model of the scene, placed by [`Scene::update`], ready to be
+    /// passed to [`crate::render_system::RenderSystem::render_model`].
+    pub fn models_mut(&mut self) -> impl Iterator<Item = &mut Model> {
+        self.update();
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| match slot.node.as_mut()?.attachment.as_mut()? {
+                Attachment::Model(model) => Some(model),
+                Attachment::Light(_) => None,
+            })
+    }
+
+    /// Every light of the scene, with its position moved to world space.
+    pub fn lights(&self) -> Vec<DirectionalLight> {
+        let mut lights = Vec::new();
+        for (index, slot) in self.slots.iter().enumerate() {
+            if let Some(Attachment::Light(light)) =
+                slot.node.as_ref().and_then(|n| n.attachment.as_ref())
+            {
+                let id = NodeId {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+                let position = self.world_matrix(id).transform_point3(light.get_position());
+                lights.push(DirectionalLight {
+                    position: position.to_array(),
+                    ..*light
+                });
+            }
+        }
+        lights
+    }
+
+    // Drops the world matrices of `node` and its descendants.
+    fn invalidate(&self, node: NodeId) {
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            // Without a world matrix, its descendants have none either.
+            if node.world.take().is_some() {
+                stack.extend(&node.children);
+            }
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        match self.slots.get(id.index as usize) {
+            Some(Slot {
+                generation,
+                node: Some(node),
+            }) if *generation == id.generation => node,
+            _ => panic!("{:?} is not part of the scene", id),
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        match self.slots.get_mut(id.index as usize) {
+            Some(Slot {
+                generation,
+                node: Some(node),
+            }) if *generation == id.generation => node,
+            _ => panic!("{:?} is not part of the scene", id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+    use crate::obj_loader::Primitive;
+
+    fn translation(x: f32, y: f32, z: f32) -> Transform {
+        Transform::from_translation(Vec3::new(x, y, z))
+    }
+
+    #[test]
+    fn moving_a_parent_moves_cached_grandchildren() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, translation(1.0, 0.0, 0.0));
+        let child = scene.add(Some(root), Transform::from_scale(Vec3::splat(2.0)));
+        let model = Model::primitive(Primitive::Cube { divisions: 1 })
+            .build()
+            .unwrap();
+        let grandchild = scene.add_model(Some(child), model);
+        scene.set_local(grandchild, translation(0.0, 1.0, 0.0));
+
+        let expected =
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 0.0)) * Mat4::from_scale(Vec3::splat(2.0));
+        assert_eq!(scene.world_matrix(grandchild), expected);
+        scene.update();
+        assert_eq!(scene.model(grandchild).unwrap().model_matrix(), expected);
+
+        // Only the root changes, while the matrices below it are cached.
+        scene.local_mut(root).translation = Vec3::new(0.0, 0.0, 5.0);
+        let expected =
+            Mat4::from_translation(Vec3::new(0.0, 2.0, 5.0)) * Mat4::from_scale(Vec3::splat(2.0));
+        assert_eq!(scene.world_matrix(grandchild), expected);
+        let model = scene.models_mut().next().unwrap();
+        assert_eq!(model.model_matrix(), expected);
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, translation(1.0, 0.0, 0.0));
+        let child = scene.add(Some(root), translation(0.0, 1.0, 0.0));
+        let grandchild = scene.add(Some(child), translation(0.0, 0.0, 1.0));
+
+        assert!(!scene.set_parent(root, Some(grandchild)));
+        assert!(!scene.set_parent(child, Some(child)));
+        assert_eq!(scene.parent(root), None);
+        assert_eq!(scene.roots(), [root]);
+        assert_eq!(scene.children(child), [grandchild]);
+
+        // Moving to the root keeps the local transform.
+        assert!(scene.set_parent(grandchild, None));
+        assert_eq!(scene.roots(), [root, grandchild]);
+        assert!(scene.children(child).is_empty());
+        assert_eq!(
+            scene.world_matrix(grandchild),
+            Mat4::from_translation(Vec3::Z)
+        );
+    }
+
+    #[test]
+    fn removed_ids_stay_invalid_after_reuse() {
+        let mut scene = Scene::new();
+        let root = scene.add(None, Transform::IDENTITY);
+        let child = scene.add(Some(root), Transform::IDENTITY);
+        let grandchild = scene.add(Some(child), Transform::IDENTITY);
+        assert_eq!(scene.len(), 3);
+
+        scene.remove(child);
+        assert_eq!(scene.len(), 1);
+        assert!(scene.contains(root));
+        assert!(!scene.contains(child));
+        assert!(!scene.contains(grandchild));
+        assert!(scene.children(root).is_empty());
+
+        // The new nodes take the freed slots, with new ids.
+        let reused = [
+            scene.add(None, Transform::IDENTITY),
+            scene.add(None, Transform::IDENTITY),
+        ];
+        assert_eq!(scene.len(), 3);
+        for id in reused {
+            assert!(scene.contains(id));
+            assert!(id != child && id != grandchild);
+        }
+        let indices = |ids: &[NodeId]| {
+            let mut indices: Vec<u32> = ids.iter().map(|id| id.index).collect();
+            indices.sort();
+            indices
+        };
+        assert_eq!(indices(&reused), indices(&[child, grandchild]));
+        assert!(!scene.contains(child));
+        assert!(!scene.contains(grandchild));
+    }
+
+    #[test]
+    fn lights_are_placed_in_world_space() {
+        let mut scene = Scene::new();
+        let rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        let root = scene.add(
+            None,
+            Transform::from_rotation(rotation).with_translation(Vec3::new(0.0, 0.0, 3.0)),
+        );
+        let light = DirectionalLight {
+            position: [1.0, 0.0, 0.0],
+            color: [1.0, 0.5, 0.25],
+        };
+        let attached = scene.add_light(Some(root), light);
+        scene.add_light(None, light);
+
+        let lights = scene.lights();
+        assert_eq!(lights.len(), 2);
+        assert!(lights[0]
+            .get_position()
+            .abs_diff_eq(Vec3::new(0.0, 1.0, 3.0), 1e-6));
+        assert_eq!(lights[0].color, light.color);
+        assert_eq!(lights[1].position, light.position);
+        // The attached light keeps its local position.
+        match scene.attachment(attached) {
+            Some(Attachment::Light(local)) => assert_eq!(local.position, light.position),
+            _ => panic!("the light is gone"),
+        }
+        assert_eq!(scene.models_mut().count(), 0);
+    }
+}
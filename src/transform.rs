@@ -0,0 +1,228 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+/// A translation, rotation and scale, applied to a point in the order
+/// scale, rotate, translate.
+///
+/// Unlike a matrix, every part can be read back and changed on its own, and
+/// two transforms can be blended without shearing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    /// Leaves points where they are.
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Transform {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Transform {
+        Transform {
+            rotation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Transform {
+        Transform {
+            scale,
+            ..Transform::IDENTITY
+        }
+    }
+
+    /// Splits an affine matrix into its parts. Shear and perspective can't
+    /// be represented and are lost.
+    pub fn from_matrix(matrix: Mat4) -> Transform {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Transform {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Transform {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Transform {
+        self.scale = scale;
+        self
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// The inverse transpose of [`Transform::matrix`], which keeps normals
+    /// perpendicular to their surface under non-uniform scale.
+    pub fn normal_matrix(&self) -> Mat4 {
+        self.matrix().inverse().transpose()
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.rotation * (point * self.scale) + self.translation
+    }
+
+    /// Like [`Transform::transform_point`], without the translation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.rotation * (vector * self.scale)
+    }
+
+    /// The direction the local -Z axis points to, the way a camera looks.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    /// Turns the transform so that [`Transform::forward`] points from the
+    /// translation at `target`, with the local Y axis as close to `up` as
+    /// possible. Nothing changes if `target` is the translation, and any
+    /// roll is picked if `up` is parallel to the view direction.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let forward = (target - self.translation).normalize_or_zero();
+        if forward == Vec3::ZERO {
+            return;
+        }
+        let right = forward.cross(up).normalize_or_zero();
+        let right = match right == Vec3::ZERO {
+            true => forward.any_orthonormal_vector(),
+            false => right,
+        };
+        let up = right.cross(forward);
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+    }
+
+    /// Same as [`Transform::look_at`], returning the turned transform.
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Transform {
+        self.look_at(target, up);
+        self
+    }
+
+    /// Blends from `self` at `t == 0.0` to `other` at `t == 1.0`. The
+    /// translation and scale are interpolated linearly, the rotation
+    /// spherically along the shortest arc.
+    pub fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn example() -> Transform {
+        Transform {
+            translation: Vec3::new(1.0, -2.0, 3.0),
+            rotation: Quat::from_euler(glam::EulerRot::YXZ, 0.3, -1.1, 0.7),
+            scale: Vec3::new(2.0, 0.5, 3.0),
+        }
+    }
+
+    #[test]
+    fn matrices_split_back_into_the_same_parts() {
+        let transform = example();
+        let split = Transform::from_matrix(transform.matrix());
+        assert!(split.translation.abs_diff_eq(transform.translation, 1e-5));
+        // q and -q are the same rotation
+        assert!(split.rotation.dot(transform.rotation).abs() > 1.0 - 1e-5);
+        assert!(split.scale.abs_diff_eq(transform.scale, 1e-5));
+        assert!(split.matrix().abs_diff_eq(transform.matrix(), 1e-5));
+
+        let point = Vec3::new(0.5, 4.0, -1.0);
+        let moved = transform.matrix().transform_point3(point);
+        assert!(transform.transform_point(point).abs_diff_eq(moved, 1e-5));
+        let turned = transform.matrix().transform_vector3(point);
+        assert!(transform.transform_vector(point).abs_diff_eq(turned, 1e-5));
+    }
+
+    #[test]
+    fn look_at_points_forward_at_the_target() {
+        let mut transform = Transform::from_translation(Vec3::new(0.0, 1.0, 5.0));
+        let target = Vec3::new(3.0, 1.0, 1.0);
+        transform.look_at(target, Vec3::Y);
+        let direction = (target - transform.translation).normalize();
+        assert!(transform.forward().abs_diff_eq(direction, 1e-5));
+        // Level, so the local Y axis is the world's
+        assert!((transform.rotation * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+
+        // Looking straight up picks some roll instead of failing
+        let up = Transform::IDENTITY.looking_at(Vec3::Y * 2.0, Vec3::Y);
+        assert!(up.forward().abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(up.rotation.is_normalized());
+
+        // Looking at itself keeps the rotation
+        let unchanged = example().looking_at(example().translation, Vec3::Y);
+        assert_eq!(unchanged, example());
+    }
+
+    #[test]
+    fn lerp_blends_every_part() {
+        let from = Transform::IDENTITY;
+        let to = Transform {
+            translation: Vec3::new(2.0, 0.0, -4.0),
+            rotation: Quat::from_rotation_y(FRAC_PI_2),
+            scale: Vec3::splat(3.0),
+        };
+        let start = from.lerp(&to, 0.0);
+        assert!(start.matrix().abs_diff_eq(from.matrix(), 1e-5));
+        let end = from.lerp(&to, 1.0);
+        assert!(end.matrix().abs_diff_eq(to.matrix(), 1e-5));
+
+        let middle = from.lerp(&to, 0.5);
+        assert!(middle.translation.abs_diff_eq(Vec3::new(1.0, 0.0, -2.0), 1e-5));
+        assert!(middle.scale.abs_diff_eq(Vec3::splat(2.0), 1e-5));
+        let halfway = Quat::from_rotation_y(FRAC_PI_2 / 2.0);
+        assert!(middle.rotation.abs_diff_eq(halfway, 1e-5));
+
+        // The same rotation with a flipped sign still takes the short way
+        let flipped = Transform::from_rotation(-to.rotation);
+        let middle = from.lerp(&flipped, 0.5);
+        assert!(middle.forward().abs_diff_eq(halfway * Vec3::NEG_Z, 1e-5));
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let transform = example();
+        // A surface spanned by two tangents, and its normal
+        let (a, b) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, -1.0));
+        let normal = a.cross(b);
+        let (a, b) = (transform.transform_vector(a), transform.transform_vector(b));
+        let moved = transform.normal_matrix().transform_vector3(normal);
+        assert!(moved.dot(a).abs() < 1e-4);
+        assert!(moved.dot(b).abs() < 1e-4);
+        assert!(moved.normalize().abs_diff_eq(a.cross(b).normalize(), 1e-5));
+
+        // The model matrix itself would tilt the normal
+        let wrong = transform.matrix().transform_vector3(normal);
+        assert!(wrong.dot(a).abs() > 0.1);
+    }
+}